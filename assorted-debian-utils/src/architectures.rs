@@ -3,22 +3,33 @@
 
 //! # Helpers to handle Debian architectures
 //!
-//! This module provides helpers for Debian architectures. This currently involves a list of release
-//! architectures and an enum for release and ports architectures.
+//! This module provides helpers for Debian architectures. This currently involves the lists of
+//! release architectures of known releases, an enum for release and ports architectures, and the
+//! mapping of architectures to their ABI/libc/OS/CPU tuples as defined by dpkg's `cputable`,
+//! `ostable` and `tupletable`. The latter are used to match architectures against wildcards.
+//!
+//! ```
+//! use assorted_debian_utils::architectures::Architecture;
+//!
+//! assert!(Architecture::Armhf.matches("any-arm"));
+//! assert!(Architecture::Armhf.matches("linux-any"));
+//! assert!(!Architecture::HurdI386.matches("linux-any"));
+//! assert_eq!(Architecture::Armhf.gnu_triplet().as_deref(), Some("arm-linux-gnueabihf"));
+//! ```
 
-use std::fmt::{Display, Formatter};
+use std::fmt::{self, Display, Formatter};
 use std::str::FromStr;
 
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Serialize};
 
 pub use crate::ParseError;
 
 /// Debian architectures
 ///
 /// This enum describes architectures that are release architectures or available on Debian ports.
-/// It also provides `All` as special case for binary independent packages.
-#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Hash, Eq)]
-#[serde(rename_all = "lowercase")]
+/// It also provides `All` as special case for binary independent packages. Other architectures
+/// are represented by `Other`.
+#[derive(Clone, Debug, PartialEq, Hash, Eq)]
 pub enum Architecture {
     /// The `all` architecture for architecture independent packages
     All,
@@ -35,20 +46,21 @@ pub enum Architecture {
     /// The `hppa` architecture
     Hppa,
     /// The `hurd-i386` architecture
-    #[serde(rename = "hurd-i386")]
     HurdI386,
     /// The `i386` architecture
     I386,
     /// The `ia64` architecture
     Ia64,
     /// The `kfreebsd-amd64` architecture
-    #[serde(rename = "kfreebsd-amd64")]
     KFreeBSDAmd64,
     /// The `kfreebsd-i386` architecture
-    #[serde(rename = "kfreebsd-i386")]
     KFreeBSDI386,
-    /// The `m86k` architecture
-    M86k,
+    /// The `loong64` architecture
+    Loong64,
+    /// The `m68k` architecture
+    M68k,
+    /// The `mips` architecture
+    Mips,
     /// The `mips64el` architecture
     Mips64el,
     /// The `mipsel` architecture
@@ -71,40 +83,146 @@ pub enum Architecture {
     X32,
     /// The `source` architecture
     Source,
+    /// Any other architecture
+    Other(String),
+}
+
+impl Architecture {
+    /// Return the name of the architecture
+    pub fn as_str(&self) -> &str {
+        match self {
+            Architecture::All => "all",
+            Architecture::Alpha => "alpha",
+            Architecture::Amd64 => "amd64",
+            Architecture::Arm64 => "arm64",
+            Architecture::Armel => "armel",
+            Architecture::Armhf => "armhf",
+            Architecture::Hppa => "hppa",
+            Architecture::HurdI386 => "hurd-i386",
+            Architecture::I386 => "i386",
+            Architecture::Ia64 => "ia64",
+            Architecture::KFreeBSDAmd64 => "kfreebsd-amd64",
+            Architecture::KFreeBSDI386 => "kfreebsd-i386",
+            Architecture::Loong64 => "loong64",
+            Architecture::M68k => "m68k",
+            Architecture::Mips => "mips",
+            Architecture::Mips64el => "mips64el",
+            Architecture::Mipsel => "mipsel",
+            Architecture::PowerPC => "powerpc",
+            Architecture::Ppc64 => "ppc64",
+            Architecture::Ppc64el => "ppc64el",
+            Architecture::Riscv64 => "riscv64",
+            Architecture::S390x => "s390x",
+            Architecture::Sh4 => "sh4",
+            Architecture::Sparc64 => "sparc64",
+            Architecture::X32 => "x32",
+            Architecture::Source => "source",
+            Architecture::Other(arch) => arch,
+        }
+    }
+
+    /// Return the ABI/libc/OS/CPU tuple of the architecture
+    ///
+    /// Returns `None` for `all`, `source` and architectures unknown to dpkg.
+    pub fn tuple(&self) -> Option<Tuple> {
+        let arch = self.as_str();
+        for (tuple, name) in TUPLETABLE {
+            let (tuple, cpu) = match tuple.strip_suffix("<cpu>") {
+                Some(tuple) => {
+                    let (prefix, suffix) = name.split_once("<cpu>").unwrap();
+                    match arch
+                        .strip_prefix(prefix)
+                        .and_then(|arch| arch.strip_suffix(suffix))
+                        .and_then(cpu_entry)
+                    {
+                        Some(cpu) => (tuple, cpu.name),
+                        None => continue,
+                    }
+                }
+                None if arch == name => {
+                    let (tuple, cpu) = tuple.rsplit_once('-').unwrap();
+                    (tuple, cpu)
+                }
+                None => continue,
+            };
+
+            let mut components = tuple.trim_end_matches('-').splitn(3, '-');
+            return Some(Tuple {
+                abi: components.next().unwrap(),
+                libc: components.next().unwrap(),
+                os: components.next().unwrap(),
+                cpu,
+            });
+        }
+        None
+    }
+
+    /// Return the GNU triplet of the architecture, e.g., `x86_64-linux-gnu` for `amd64`
+    pub fn gnu_triplet(&self) -> Option<String> {
+        let tuple = self.tuple()?;
+        let cpu = cpu_entry(tuple.cpu)?;
+        let os = OSTABLE
+            .iter()
+            .find(|(os_tuple, _)| *os_tuple == format!("{}-{}-{}", tuple.abi, tuple.libc, tuple.os))
+            .map(|(_, os)| os)?;
+        Some(format!("{}-{}", cpu.gnu_name, os))
+    }
+
+    /// Return the number of bits of the architecture's CPU
+    pub fn bits(&self) -> Option<u32> {
+        self.tuple()
+            .and_then(|tuple| cpu_entry(tuple.cpu))
+            .map(|cpu| cpu.bits)
+    }
+
+    /// Return the endianness of the architecture's CPU
+    pub fn endianness(&self) -> Option<Endianness> {
+        self.tuple()
+            .and_then(|tuple| cpu_entry(tuple.cpu))
+            .map(|cpu| cpu.endianness)
+    }
+
+    /// Check whether the architecture matches the given architecture or wildcard
+    ///
+    /// Wildcards such as `any`, `linux-any`, `any-amd64` or `gnu-any-any` are handled as by
+    /// dpkg: each part of the wildcard's tuple is either `any` or has to match the corresponding
+    /// part of the architecture's tuple.
+    pub fn matches(&self, wildcard: &str) -> bool {
+        let arch = self.as_str();
+        if arch == wildcard || wildcard == "any" {
+            return true;
+        }
+
+        let tuple = match self.tuple() {
+            Some(tuple) => tuple,
+            None => return false,
+        };
+        match wildcard_tuple(wildcard) {
+            Some(wildcard) => [tuple.abi, tuple.libc, tuple.os, tuple.cpu]
+                .iter()
+                .zip(wildcard.iter())
+                .all(|(part, wildcard_part)| *wildcard_part == "any" || part == wildcard_part),
+            None => false,
+        }
+    }
+
+    /// Check whether the architecture is a release architecture of unstable
+    pub fn is_release(&self) -> bool {
+        UNSTABLE_ARCHITECTURES.contains(self)
+    }
+
+    /// Check whether the architecture is a ports architecture
+    ///
+    /// All architectures that are not release architectures of unstable are considered to be
+    /// ports architectures. `all` and `source` are neither.
+    pub fn is_ports(&self) -> bool {
+        !self.is_release() && *self != Architecture::All && *self != Architecture::Source
+    }
 }
 
 impl Display for Architecture {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{}",
-            match self {
-                Architecture::All => "all",
-                Architecture::Alpha => "alpha",
-                Architecture::Amd64 => "amd64",
-                Architecture::Arm64 => "arm64",
-                Architecture::Armel => "armel",
-                Architecture::Armhf => "armhf",
-                Architecture::Hppa => "hppa",
-                Architecture::HurdI386 => "hurd-i386",
-                Architecture::I386 => "i386",
-                Architecture::Ia64 => "ia64",
-                Architecture::KFreeBSDAmd64 => "kfreebsd-amd64",
-                Architecture::KFreeBSDI386 => "kfreebsd-i386",
-                Architecture::M86k => "m86k",
-                Architecture::Mips64el => "mips64el",
-                Architecture::Mipsel => "mipsel",
-                Architecture::PowerPC => "powerpc",
-                Architecture::Ppc64 => "ppc64",
-                Architecture::Ppc64el => "ppc64el",
-                Architecture::Riscv64 => "risc64",
-                Architecture::S390x => "s390x",
-                Architecture::Sh4 => "sh4",
-                Architecture::Sparc64 => "sparc64",
-                Architecture::X32 => "x32",
-                Architecture::Source => "source",
-            }
-        )
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
     }
 }
 
@@ -125,19 +243,33 @@ impl TryFrom<&str> for Architecture {
             "ia64" => Ok(Architecture::Ia64),
             "kfreebsd-amd64" => Ok(Architecture::KFreeBSDAmd64),
             "kfreebsd-i386" => Ok(Architecture::KFreeBSDI386),
-            "m86k" => Ok(Architecture::M86k),
+            "loong64" => Ok(Architecture::Loong64),
+            "m68k" => Ok(Architecture::M68k),
+            "mips" => Ok(Architecture::Mips),
             "mips64el" => Ok(Architecture::Mips64el),
             "mipsel" => Ok(Architecture::Mipsel),
             "powerpc" => Ok(Architecture::PowerPC),
             "ppc64" => Ok(Architecture::Ppc64),
             "ppc64el" => Ok(Architecture::Ppc64el),
-            "risc64" => Ok(Architecture::Riscv64),
+            "riscv64" => Ok(Architecture::Riscv64),
             "s390x" => Ok(Architecture::S390x),
             "sh4" => Ok(Architecture::Sh4),
             "sparc64" => Ok(Architecture::Sparc64),
             "x32" => Ok(Architecture::X32),
             "source" => Ok(Architecture::Source),
-            _ => Err(ParseError::InvalidArchitecture),
+            _ => {
+                // wildcards are not architectures
+                if value.is_empty()
+                    || is_wildcard(value)
+                    || !value
+                        .chars()
+                        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+                {
+                    Err(ParseError::InvalidArchitecture)
+                } else {
+                    Ok(Architecture::Other(value.to_owned()))
+                }
+            }
         }
     }
 }
@@ -150,7 +282,227 @@ impl FromStr for Architecture {
     }
 }
 
+impl Serialize for Architecture {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Architecture {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct ArchitectureVisitor;
+
+        impl<'de> de::Visitor<'de> for ArchitectureVisitor {
+            type Value = Architecture;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write!(formatter, "an architecture")
+            }
+
+            fn visit_str<E>(self, s: &str) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Architecture::try_from(s)
+                    .map_err(|_| de::Error::invalid_value(de::Unexpected::Str(s), &self))
+            }
+        }
+
+        deserializer.deserialize_str(ArchitectureVisitor)
+    }
+}
+
+/// Endianness of a CPU
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Endianness {
+    /// Little endian
+    Little,
+    /// Big endian
+    Big,
+}
+
+/// ABI/libc/OS/CPU tuple of an architecture
+///
+/// For example, `armhf` is represented by the tuple `eabihf-gnu-linux-arm`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Tuple {
+    /// The ABI, e.g., `base` or `eabihf`
+    pub abi: &'static str,
+    /// The libc, e.g., `gnu` or `musl`
+    pub libc: &'static str,
+    /// The OS, e.g., `linux` or `hurd`
+    pub os: &'static str,
+    /// The CPU, e.g., `amd64` or `arm`
+    pub cpu: &'static str,
+}
+
+impl Display for Tuple {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}-{}-{}", self.abi, self.libc, self.os, self.cpu)
+    }
+}
+
+/// Check whether the given string is an architecture wildcard, e.g., `any` or `linux-any`
+pub fn is_wildcard(value: &str) -> bool {
+    wildcard_tuple(value).is_some_and(|tuple| tuple.contains(&"any"))
+}
+
+// Expand a wildcard to a tuple as done by dpkg's debwildcard_to_debtuple
+fn wildcard_tuple(wildcard: &str) -> Option<[&str; 4]> {
+    let parts: Vec<&str> = wildcard.splitn(4, '-').collect();
+    if !parts.contains(&"any") {
+        return None;
+    }
+    match parts[..] {
+        [abi, libc, os, cpu] => Some([abi, libc, os, cpu]),
+        [libc, os, cpu] => Some(["any", libc, os, cpu]),
+        [os, cpu] => Some(["any", "any", os, cpu]),
+        _ => Some(["any", "any", "any", "any"]),
+    }
+}
+
+struct CpuEntry {
+    name: &'static str,
+    gnu_name: &'static str,
+    bits: u32,
+    endianness: Endianness,
+}
+
+const fn cpu(
+    name: &'static str,
+    gnu_name: &'static str,
+    bits: u32,
+    endianness: Endianness,
+) -> CpuEntry {
+    CpuEntry {
+        name,
+        gnu_name,
+        bits,
+        endianness,
+    }
+}
+
+// Mirrors dpkg's cputable
+const CPUTABLE: [CpuEntry; 37] = [
+    cpu("alpha", "alpha", 64, Endianness::Little),
+    cpu("amd64", "x86_64", 64, Endianness::Little),
+    cpu("arc", "arc", 32, Endianness::Little),
+    cpu("armeb", "armeb", 32, Endianness::Big),
+    cpu("arm", "arm", 32, Endianness::Little),
+    cpu("arm64", "aarch64", 64, Endianness::Little),
+    cpu("avr32", "avr32", 32, Endianness::Big),
+    cpu("hppa", "hppa", 32, Endianness::Big),
+    cpu("loong64", "loongarch64", 64, Endianness::Little),
+    cpu("i386", "i686", 32, Endianness::Little),
+    cpu("ia64", "ia64", 64, Endianness::Little),
+    cpu("m32r", "m32r", 32, Endianness::Big),
+    cpu("m68k", "m68k", 32, Endianness::Big),
+    cpu("mips", "mips", 32, Endianness::Big),
+    cpu("mipsel", "mipsel", 32, Endianness::Little),
+    cpu("mipsr6", "mipsisa32r6", 32, Endianness::Big),
+    cpu("mipsr6el", "mipsisa32r6el", 32, Endianness::Little),
+    cpu("mips64", "mips64", 64, Endianness::Big),
+    cpu("mips64el", "mips64el", 64, Endianness::Little),
+    cpu("mips64r6", "mipsisa64r6", 64, Endianness::Big),
+    cpu("mips64r6el", "mipsisa64r6el", 64, Endianness::Little),
+    cpu("nios2", "nios2", 32, Endianness::Little),
+    cpu("or1k", "or1k", 32, Endianness::Big),
+    cpu("powerpc", "powerpc", 32, Endianness::Big),
+    cpu("powerpcel", "powerpcle", 32, Endianness::Little),
+    cpu("ppc64", "powerpc64", 64, Endianness::Big),
+    cpu("ppc64el", "powerpc64le", 64, Endianness::Little),
+    cpu("riscv64", "riscv64", 64, Endianness::Little),
+    cpu("s390", "s390", 32, Endianness::Big),
+    cpu("s390x", "s390x", 64, Endianness::Big),
+    cpu("sh3", "sh3", 32, Endianness::Little),
+    cpu("sh3eb", "sh3eb", 32, Endianness::Big),
+    cpu("sh4", "sh4", 32, Endianness::Little),
+    cpu("sh4eb", "sh4eb", 32, Endianness::Big),
+    cpu("sparc", "sparc", 32, Endianness::Big),
+    cpu("sparc64", "sparc64", 64, Endianness::Big),
+    cpu("tilegx", "tilegx", 64, Endianness::Little),
+];
+
+fn cpu_entry(name: &str) -> Option<&'static CpuEntry> {
+    CPUTABLE.iter().find(|cpu| cpu.name == name)
+}
+
+// Mirrors dpkg's ostable: Debian tuple without the CPU and GNU system name
+const OSTABLE: [(&str, &str); 27] = [
+    ("eabi-uclibc-linux", "linux-uclibceabi"),
+    ("base-uclibc-linux", "linux-uclibc"),
+    ("eabihf-musl-linux", "linux-musleabihf"),
+    ("base-musl-linux", "linux-musl"),
+    ("eabihf-gnu-linux", "linux-gnueabihf"),
+    ("eabi-gnu-linux", "linux-gnueabi"),
+    ("abin32-gnu-linux", "linux-gnuabin32"),
+    ("abi64-gnu-linux", "linux-gnuabi64"),
+    ("spe-gnu-linux", "linux-gnuspe"),
+    ("x32-gnu-linux", "linux-gnux32"),
+    ("ilp32-gnu-linux", "linux-gnu_ilp32"),
+    ("base-gnu-linux", "linux-gnu"),
+    ("eabihf-gnu-kfreebsd", "kfreebsd-gnueabihf"),
+    ("base-gnu-kfreebsd", "kfreebsd-gnu"),
+    ("base-gnu-knetbsd", "knetbsd-gnu"),
+    ("base-gnu-kopensolaris", "kopensolaris-gnu"),
+    ("base-gnu-hurd", "gnu"),
+    ("base-bsd-darwin", "darwin"),
+    ("base-bsd-dragonflybsd", "dragonflybsd"),
+    ("base-bsd-freebsd", "freebsd"),
+    ("base-bsd-netbsd", "netbsd"),
+    ("base-bsd-openbsd", "openbsd"),
+    ("base-sysv-aix", "aix"),
+    ("base-sysv-solaris", "solaris"),
+    ("eabi-uclibc-uclinux", "uclinux-uclibceabi"),
+    ("base-uclibc-uclinux", "uclinux-uclibc"),
+    ("base-tos-mint", "mint"),
+];
+
+// Mirrors dpkg's tupletable: Debian tuple and architecture name
+const TUPLETABLE: [(&str, &str); 33] = [
+    ("eabi-uclibc-linux-arm", "uclibc-linux-armel"),
+    ("base-uclibc-linux-<cpu>", "uclibc-linux-<cpu>"),
+    ("eabihf-musl-linux-arm", "musl-linux-armhf"),
+    ("base-musl-linux-<cpu>", "musl-linux-<cpu>"),
+    ("ilp32-gnu-linux-arm64", "arm64ilp32"),
+    ("eabihf-gnu-linux-arm", "armhf"),
+    ("eabi-gnu-linux-arm", "armel"),
+    ("abin32-gnu-linux-mips64r6el", "mipsn32r6el"),
+    ("abin32-gnu-linux-mips64r6", "mipsn32r6"),
+    ("abin32-gnu-linux-mips64el", "mipsn32el"),
+    ("abin32-gnu-linux-mips64", "mipsn32"),
+    ("abi64-gnu-linux-mips64r6el", "mips64r6el"),
+    ("abi64-gnu-linux-mips64r6", "mips64r6"),
+    ("abi64-gnu-linux-mips64el", "mips64el"),
+    ("abi64-gnu-linux-mips64", "mips64"),
+    ("spe-gnu-linux-powerpc", "powerpcspe"),
+    ("x32-gnu-linux-amd64", "x32"),
+    ("base-gnu-linux-<cpu>", "<cpu>"),
+    ("eabihf-gnu-kfreebsd-arm", "kfreebsd-armhf"),
+    ("base-gnu-kfreebsd-<cpu>", "kfreebsd-<cpu>"),
+    ("base-gnu-knetbsd-<cpu>", "knetbsd-<cpu>"),
+    ("base-gnu-kopensolaris-<cpu>", "kopensolaris-<cpu>"),
+    ("base-gnu-hurd-<cpu>", "hurd-<cpu>"),
+    ("base-bsd-dragonflybsd-<cpu>", "dragonflybsd-<cpu>"),
+    ("base-bsd-freebsd-<cpu>", "freebsd-<cpu>"),
+    ("base-bsd-openbsd-<cpu>", "openbsd-<cpu>"),
+    ("base-bsd-netbsd-<cpu>", "netbsd-<cpu>"),
+    ("base-bsd-darwin-<cpu>", "darwin-<cpu>"),
+    ("base-sysv-aix-<cpu>", "aix-<cpu>"),
+    ("base-sysv-solaris-<cpu>", "solaris-<cpu>"),
+    ("eabi-uclibc-uclinux-arm", "uclinux-armel"),
+    ("base-uclibc-uclinux-<cpu>", "uclinux-<cpu>"),
+    ("base-tos-mint-m68k", "mint-m68k"),
+];
+
 /// Release architectures for bookworm
+#[deprecated(note = "use release_architectures to obtain the architectures of a release")]
 pub const RELEASE_ARCHITECTURES: [Architecture; 9] = [
     Architecture::Amd64,
     Architecture::Arm64,
@@ -163,9 +515,90 @@ pub const RELEASE_ARCHITECTURES: [Architecture; 9] = [
     Architecture::S390x,
 ];
 
+static STRETCH_ARCHITECTURES: [Architecture; 10] = [
+    Architecture::Amd64,
+    Architecture::Arm64,
+    Architecture::Armel,
+    Architecture::Armhf,
+    Architecture::I386,
+    Architecture::Mips,
+    Architecture::Mips64el,
+    Architecture::Mipsel,
+    Architecture::Ppc64el,
+    Architecture::S390x,
+];
+
+static BULLSEYE_ARCHITECTURES: [Architecture; 9] = [
+    Architecture::Amd64,
+    Architecture::Arm64,
+    Architecture::Armel,
+    Architecture::Armhf,
+    Architecture::I386,
+    Architecture::Mips64el,
+    Architecture::Mipsel,
+    Architecture::Ppc64el,
+    Architecture::S390x,
+];
+
+static BOOKWORM_ARCHITECTURES: [Architecture; 8] = [
+    Architecture::Amd64,
+    Architecture::Arm64,
+    Architecture::Armel,
+    Architecture::Armhf,
+    Architecture::I386,
+    Architecture::Mips64el,
+    Architecture::Ppc64el,
+    Architecture::S390x,
+];
+
+static TRIXIE_ARCHITECTURES: [Architecture; 8] = [
+    Architecture::Amd64,
+    Architecture::Arm64,
+    Architecture::Armel,
+    Architecture::Armhf,
+    Architecture::I386,
+    Architecture::Ppc64el,
+    Architecture::Riscv64,
+    Architecture::S390x,
+];
+
+// Release architectures of unstable; used to classify architectures, not to determine the
+// architectures available in unstable
+static UNSTABLE_ARCHITECTURES: &[Architecture] = &TRIXIE_ARCHITECTURES;
+
+/// Release architectures of a release given by its codename
+///
+/// This is a built-in table for known releases. The `Architectures` field of the release file
+/// should be preferred if it is available. Codenames with an extension, e.g.,
+/// `bookworm-backports`, share the architectures of their base release. The architectures of
+/// unstable and experimental change independently of releases and are not included; they need
+/// to be taken from the release file.
+///
+/// ```
+/// use assorted_debian_utils::architectures::{release_architectures, Architecture};
+///
+/// let architectures = release_architectures("bookworm").expect("Unknown release");
+/// assert!(architectures.contains(&Architecture::Mips64el));
+/// assert!(!architectures.contains(&Architecture::Riscv64));
+/// assert_eq!(release_architectures("bookworm-backports"), Some(architectures));
+/// assert!(release_architectures("sid").is_none());
+/// ```
+pub fn release_architectures(codename: &str) -> Option<&'static [Architecture]> {
+    match codename {
+        "stretch" | "buster" => Some(&STRETCH_ARCHITECTURES),
+        "bullseye" => Some(&BULLSEYE_ARCHITECTURES),
+        "bookworm" => Some(&BOOKWORM_ARCHITECTURES),
+        "trixie" => Some(&TRIXIE_ARCHITECTURES),
+        "sid" | "rc-buggy" => None,
+        _ => codename
+            .split_once('-')
+            .and_then(|(codename, _)| release_architectures(codename)),
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use super::Architecture;
+    use super::{is_wildcard, release_architectures, Architecture, Endianness};
 
     #[test]
     fn from_str() {
@@ -173,5 +606,116 @@ mod test {
             Architecture::try_from("amd64").unwrap(),
             Architecture::Amd64
         );
+        assert_eq!(
+            Architecture::try_from("riscv64").unwrap(),
+            Architecture::Riscv64
+        );
+        assert_eq!(Architecture::Riscv64.to_string(), "riscv64");
+        assert_eq!(
+            Architecture::try_from("loong64").unwrap(),
+            Architecture::Loong64
+        );
+    }
+
+    #[test]
+    fn other() {
+        let arch = Architecture::try_from("hurd-amd64").unwrap();
+        assert_eq!(arch, Architecture::Other("hurd-amd64".into()));
+        assert_eq!(arch.to_string(), "hurd-amd64");
+        assert!(Architecture::try_from("").is_err());
+        assert!(Architecture::try_from("linux-any").is_err());
+        assert!(Architecture::try_from("amd64 i386").is_err());
+    }
+
+    #[test]
+    fn tuple() {
+        assert_eq!(
+            Architecture::Amd64.tuple().unwrap().to_string(),
+            "base-gnu-linux-amd64"
+        );
+        assert_eq!(
+            Architecture::Armhf.tuple().unwrap().to_string(),
+            "eabihf-gnu-linux-arm"
+        );
+        assert_eq!(
+            Architecture::HurdI386.tuple().unwrap().to_string(),
+            "base-gnu-hurd-i386"
+        );
+        assert_eq!(
+            Architecture::X32.tuple().unwrap().to_string(),
+            "x32-gnu-linux-amd64"
+        );
+        assert!(Architecture::All.tuple().is_none());
+        assert!(Architecture::Other("foo".into()).tuple().is_none());
+
+        assert_eq!(
+            Architecture::Amd64.gnu_triplet().as_deref(),
+            Some("x86_64-linux-gnu")
+        );
+        assert_eq!(
+            Architecture::Mips64el.gnu_triplet().as_deref(),
+            Some("mips64el-linux-gnuabi64")
+        );
+        assert_eq!(
+            Architecture::HurdI386.gnu_triplet().as_deref(),
+            Some("i686-gnu")
+        );
+        assert_eq!(Architecture::S390x.bits(), Some(64));
+        assert_eq!(Architecture::S390x.endianness(), Some(Endianness::Big));
+        assert_eq!(Architecture::Armel.bits(), Some(32));
+    }
+
+    #[test]
+    fn wildcards() {
+        assert!(is_wildcard("any"));
+        assert!(is_wildcard("linux-any"));
+        assert!(is_wildcard("any-amd64"));
+        assert!(!is_wildcard("amd64"));
+        assert!(!is_wildcard("hurd-i386"));
+
+        assert!(Architecture::Amd64.matches("any"));
+        assert!(Architecture::Amd64.matches("amd64"));
+        assert!(Architecture::Amd64.matches("any-amd64"));
+        assert!(Architecture::Amd64.matches("linux-any"));
+        assert!(Architecture::Amd64.matches("gnu-linux-any"));
+        assert!(Architecture::KFreeBSDAmd64.matches("any-amd64"));
+        assert!(!Architecture::KFreeBSDAmd64.matches("linux-any"));
+        assert!(Architecture::Armel.matches("any-arm"));
+        assert!(Architecture::Armhf.matches("any-arm"));
+        assert!(!Architecture::Arm64.matches("any-arm"));
+        assert!(Architecture::HurdI386.matches("hurd-any"));
+        assert!(Architecture::X32.matches("any-amd64"));
+        assert!(!Architecture::I386.matches("amd64"));
+        assert!(!Architecture::All.matches("linux-any"));
+    }
+
+    #[test]
+    fn release_and_ports() {
+        assert!(Architecture::Amd64.is_release());
+        assert!(!Architecture::Amd64.is_ports());
+        assert!(Architecture::Loong64.is_ports());
+        assert!(Architecture::HurdI386.is_ports());
+        assert!(!Architecture::All.is_ports());
+        assert!(!Architecture::All.is_release());
+    }
+
+    #[test]
+    fn release() {
+        assert!(release_architectures("stretch")
+            .unwrap()
+            .contains(&Architecture::Mips));
+        assert!(release_architectures("trixie")
+            .unwrap()
+            .contains(&Architecture::Riscv64));
+        assert!(!release_architectures("trixie")
+            .unwrap()
+            .contains(&Architecture::Mipsel));
+        assert_eq!(
+            release_architectures("bullseye-security"),
+            release_architectures("bullseye")
+        );
+        assert!(release_architectures("sid").is_none());
+        assert!(release_architectures("rc-buggy").is_none());
+        assert!(release_architectures("hamm").is_none());
     }
 }
@@ -134,9 +134,12 @@ impl FromStr for Suite {
 
 /// Debian archive codenames
 ///
-/// This enum describes the codenames names found in the Debian archive.
-#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Hash, Eq)]
-#[serde(rename_all = "lowercase")]
+/// Only the codenames of unstable and experimental are fixed. The codenames of all other suites
+/// change with every release and need to be resolved at runtime, e.g., with
+/// [SuiteMapping](crate::release::SuiteMapping). The codenames of testing, stable and oldstable
+/// known to this crate are only used as fallback if no release files are available.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Hash, Eq)]
+#[serde(try_from = "String", into = "String")]
 pub enum Codename {
     /// The unstable suite
     Sid,
@@ -148,6 +151,8 @@ pub enum Codename {
     Stretch(Option<Extension>),
     /// The experimental suite
     RCBuggy,
+    /// Any other codename, e.g., `trixie` or `trixie-backports`
+    Other(String),
 }
 
 impl Display for Codename {
@@ -161,6 +166,7 @@ impl Display for Codename {
             Codename::Bookworm(Some(ext)) => write!(f, "bookworm-{}", ext),
             Codename::Bullseye(Some(ext)) => write!(f, "bullseye-{}", ext),
             Codename::Stretch(Some(ext)) => write!(f, "stretch-{}", ext),
+            Codename::Other(codename) => write!(f, "{}", codename),
         }
     }
 }
@@ -176,19 +182,37 @@ impl TryFrom<&str> for Codename {
             "stretch" => Ok(Codename::Stretch(None)),
             "rc-buggy" => Ok(Codename::RCBuggy),
             _ => {
-                let s = value.split_once('-').ok_or(ParseError::InvalidCodename)?;
-                let ext = Extension::try_from(s.1)?;
-                match s.0 {
-                    "bookworm" => Ok(Codename::Bookworm(Some(ext))),
-                    "bullseye" => Ok(Codename::Bullseye(Some(ext))),
-                    "stretch" => Ok(Codename::Stretch(Some(ext))),
-                    _ => Err(ParseError::InvalidCodename),
+                // suite names are not codenames
+                if Suite::try_from(value).is_ok() {
+                    return Err(ParseError::InvalidCodename);
+                }
+                let (name, ext) = match value.split_once('-') {
+                    Some((name, ext)) => (name, Some(Extension::try_from(ext)?)),
+                    None => (value, None),
+                };
+                match name {
+                    "bookworm" => Ok(Codename::Bookworm(ext)),
+                    "bullseye" => Ok(Codename::Bullseye(ext)),
+                    "stretch" => Ok(Codename::Stretch(ext)),
+                    "" | "sid" => Err(ParseError::InvalidCodename),
+                    _ if !name.chars().all(|c| c.is_ascii_lowercase()) => {
+                        Err(ParseError::InvalidCodename)
+                    }
+                    _ => Ok(Codename::Other(value.to_owned())),
                 }
             }
         }
     }
 }
 
+impl TryFrom<String> for Codename {
+    type Error = ParseError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Codename::try_from(value.as_str())
+    }
+}
+
 impl FromStr for Codename {
     type Err = ParseError;
 
@@ -197,6 +221,12 @@ impl FromStr for Codename {
     }
 }
 
+impl From<Codename> for String {
+    fn from(codename: Codename) -> Self {
+        codename.to_string()
+    }
+}
+
 impl From<Suite> for Codename {
     fn from(suite: Suite) -> Self {
         match suite {
@@ -209,14 +239,21 @@ impl From<Suite> for Codename {
     }
 }
 
-impl From<Codename> for Suite {
-    fn from(codename: Codename) -> Self {
+impl TryFrom<Codename> for Suite {
+    type Error = ParseError;
+
+    /// Convert a codename to a suite
+    ///
+    /// Codenames unknown to this crate cannot be converted. They need to be resolved with
+    /// [SuiteMapping](crate::release::SuiteMapping).
+    fn try_from(codename: Codename) -> Result<Self, Self::Error> {
         match codename {
-            Codename::Sid => Suite::Unstable,
-            Codename::Bookworm(ext) => Suite::Testing(ext),
-            Codename::Bullseye(ext) => Suite::Stable(ext),
-            Codename::Stretch(ext) => Suite::OldStable(ext),
-            Codename::RCBuggy => Suite::Experimental,
+            Codename::Sid => Ok(Suite::Unstable),
+            Codename::Bookworm(ext) => Ok(Suite::Testing(ext)),
+            Codename::Bullseye(ext) => Ok(Suite::Stable(ext)),
+            Codename::Stretch(ext) => Ok(Suite::OldStable(ext)),
+            Codename::RCBuggy => Ok(Suite::Experimental),
+            Codename::Other(_) => Err(ParseError::InvalidCodename),
         }
     }
 }
@@ -224,7 +261,7 @@ impl From<Codename> for Suite {
 /// Represents either a suite or codename
 ///
 /// This enum is useful whenever a suite name or codename works
-#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Hash, Eq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Hash, Eq)]
 pub enum SuiteOrCodename {
     /// A suite
     Suite(Suite),
@@ -275,24 +312,6 @@ impl Display for SuiteOrCodename {
     }
 }
 
-impl From<SuiteOrCodename> for Suite {
-    fn from(value: SuiteOrCodename) -> Self {
-        match value {
-            SuiteOrCodename::Suite(suite) => suite,
-            SuiteOrCodename::Codename(codename) => Suite::from(codename),
-        }
-    }
-}
-
-impl From<SuiteOrCodename> for Codename {
-    fn from(value: SuiteOrCodename) -> Self {
-        match value {
-            SuiteOrCodename::Suite(suite) => Codename::from(suite),
-            SuiteOrCodename::Codename(codename) => codename,
-        }
-    }
-}
-
 /// Allowed values of the multi-arch field
 #[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
 #[serde(rename_all = "lowercase")]
@@ -351,6 +370,9 @@ pub enum Component {
     /// The `non-free` archive component
     #[serde(rename = "non-free")]
     NonFree,
+    /// The `non-free-firmware` archive component
+    #[serde(rename = "non-free-firmware")]
+    NonFreeFirmware,
 }
 
 impl Display for Component {
@@ -359,6 +381,7 @@ impl Display for Component {
             Component::Main => write!(f, "main"),
             Component::Contrib => write!(f, "contrib"),
             Component::NonFree => write!(f, "non-free"),
+            Component::NonFreeFirmware => write!(f, "non-free-firmware"),
         }
     }
 }
@@ -371,6 +394,7 @@ impl TryFrom<&str> for Component {
             "main" => Ok(Component::Main),
             "contrib" => Ok(Component::Contrib),
             "non-free" => Ok(Component::NonFree),
+            "non-free-firmware" => Ok(Component::NonFreeFirmware),
             _ => Err(ParseError::InvalidComponent),
         }
     }
@@ -384,6 +408,51 @@ impl FromStr for Component {
     }
 }
 
+/// A checksum entry of a file as found in `.buildinfo`, `.changes` or `Release` files
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Checksum {
+    /// The checksum as hex string
+    pub checksum: String,
+    /// Size of the file
+    pub size: u64,
+    /// The file name
+    pub filename: String,
+}
+
+impl Display for Checksum {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} {}", self.checksum, self.size, self.filename)
+    }
+}
+
+impl TryFrom<&str> for Checksum {
+    type Error = ParseError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let mut split = value.split_whitespace();
+        match (split.next(), split.next(), split.next(), split.next()) {
+            (Some(checksum), Some(size), Some(filename), None)
+                if checksum.chars().all(|c| c.is_ascii_hexdigit()) =>
+            {
+                Ok(Self {
+                    checksum: checksum.to_owned(),
+                    size: size.parse().map_err(|_| ParseError::InvalidChecksum)?,
+                    filename: filename.to_owned(),
+                })
+            }
+            _ => Err(ParseError::InvalidChecksum),
+        }
+    }
+}
+
+impl FromStr for Checksum {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Checksum::try_from(s)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -401,6 +470,15 @@ mod test {
     #[test]
     fn codename_from_str() {
         assert_eq!(Codename::try_from("sid").unwrap(), Codename::Sid);
+        assert_eq!(Codename::try_from("rc-buggy").unwrap(), Codename::RCBuggy);
+        assert_eq!(
+            Codename::try_from("trixie").unwrap(),
+            Codename::Other("trixie".into())
+        );
+        assert_eq!(
+            Codename::try_from("trixie-backports").unwrap(),
+            Codename::Other("trixie-backports".into())
+        );
         assert_eq!(
             Codename::try_from("bullseye").unwrap(),
             Codename::Bullseye(None)
@@ -409,6 +487,11 @@ mod test {
             Codename::try_from("bullseye-backports").unwrap(),
             Codename::Bullseye(Some(Extension::Backports))
         );
+        assert!(Codename::try_from("testing").is_err());
+        assert!(Codename::try_from("sid-backports").is_err());
+        assert!(Codename::try_from("trixie-foo").is_err());
+        assert!(Codename::try_from("Trixie").is_err());
+        assert!(Codename::try_from("").is_err());
     }
 
     #[test]
@@ -422,11 +505,12 @@ mod test {
 
     #[test]
     fn suite_from_codename() {
-        assert_eq!(Suite::from(Codename::Sid), Suite::Unstable);
+        assert_eq!(Suite::try_from(Codename::Sid).unwrap(), Suite::Unstable);
         assert_eq!(
-            Suite::from(Codename::Bullseye(Some(Extension::Backports))),
+            Suite::try_from(Codename::Bullseye(Some(Extension::Backports))).unwrap(),
             Suite::Stable(Some(Extension::Backports))
         );
+        assert!(Suite::try_from(Codename::Other("trixie".into())).is_err());
     }
 
     #[test]
@@ -439,6 +523,11 @@ mod test {
             SuiteOrCodename::try_from("sid").unwrap(),
             SuiteOrCodename::from(Codename::Sid)
         );
+        assert_eq!(
+            SuiteOrCodename::try_from("trixie").unwrap(),
+            SuiteOrCodename::from(Codename::Other("trixie".into()))
+        );
+        assert!(SuiteOrCodename::try_from("trixie/updates").is_err());
     }
 
     #[test]
@@ -450,4 +539,16 @@ mod test {
     fn compoment_from_str() {
         assert_eq!(Component::try_from("main").unwrap(), Component::Main);
     }
+
+    #[test]
+    fn checksum_from_str() {
+        let checksum = Checksum::try_from(
+            "8b2a1969501be49fe11e8e8005bf9a3aac0e073d4c7fd97dcb8bfb6f8c9a222a 951 picnic_3.0.11-1.dsc",
+        )
+        .unwrap();
+        assert_eq!(checksum.size, 951);
+        assert_eq!(checksum.filename, "picnic_3.0.11-1.dsc");
+        assert!(Checksum::try_from("abcd 951").is_err());
+        assert!(Checksum::try_from("xyz 951 foo.dsc").is_err());
+    }
 }
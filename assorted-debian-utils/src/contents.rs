@@ -0,0 +1,358 @@
+// Copyright 2022 Sebastian Ramacher
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! # Helpers to handle `Contents` files
+//!
+//! This module provides helpers to parse `Contents-<arch>` and `Contents-udeb-<arch>` files.
+//! Each line consists of a path and a comma-separated list of packages qualified by their
+//! section (e.g., `libs/libfoo1` or `non-free/utils/foo`). As paths may contain spaces, lines
+//! are split at the last run of whitespace.
+//!
+//! The entries can either be processed one at a time with [from_reader] and [from_file], or
+//! collected into a [ContentsIndex] to look up the packages shipping a path and the paths
+//! shipped by a package.
+//!
+//! ```
+//! use assorted_debian_utils::contents::{self, ContentsIndex};
+//!
+//! let data = "usr/bin/foo bar    utils/foo\nusr/lib/libfoo.so.1    libs/libfoo1,non-free/libs/libfoo1-extra\n";
+//! let index = ContentsIndex::from_entries(contents::from_reader(data.as_bytes()))
+//!     .expect("Failed to parse Contents file");
+//! assert!(index.packages_for_path("usr/bin/foo bar").unwrap().contains("foo"));
+//! assert!(index.paths_for_package("libfoo1-extra").unwrap().contains("usr/lib/libfoo.so.1"));
+//! ```
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::{Display, Formatter},
+    io::{self, BufRead},
+    path::Path,
+};
+
+use crate::{
+    contents_diff::{ContentsMap, Packages},
+    stanzas, ParseError,
+};
+
+/// Error when reading `Contents` files
+#[derive(Debug)]
+pub enum Error {
+    /// Reading from the underlying reader failed
+    Io(io::Error),
+    /// Parsing a line failed
+    Parse(ParseError),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "unable to read Contents entry: {}", err),
+            Error::Parse(err) => write!(f, "unable to parse Contents entry: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(err: ParseError) -> Self {
+        Error::Parse(err)
+    }
+}
+
+/// A package shipping a file
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ContentsPackage {
+    /// Section of the package including the component if not in main, e.g., `non-free/libs`
+    pub section: Option<String>,
+    /// Name of the package
+    pub package: String,
+}
+
+impl TryFrom<&str> for ContentsPackage {
+    type Error = ParseError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let value = value.trim();
+        let (section, package) = match value.rsplit_once('/') {
+            Some((section, package)) => (Some(section.to_owned()), package),
+            None => (None, value),
+        };
+        if package.is_empty() || section.as_deref() == Some("") {
+            return Err(ParseError::InvalidContentsEntry);
+        }
+
+        Ok(Self {
+            section,
+            package: package.to_owned(),
+        })
+    }
+}
+
+impl Display for ContentsPackage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.section {
+            Some(section) => write!(f, "{}/{}", section, self.package),
+            None => write!(f, "{}", self.package),
+        }
+    }
+}
+
+/// A line of a `Contents` file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContentsEntry {
+    /// Path of the file (without leading slash)
+    pub path: String,
+    /// Packages shipping the file
+    pub packages: Vec<ContentsPackage>,
+}
+
+impl ContentsEntry {
+    /// Iterate over the names of the packages shipping the file
+    pub fn package_names(&self) -> impl Iterator<Item = &str> {
+        self.packages.iter().map(|package| package.package.as_str())
+    }
+}
+
+impl TryFrom<&str> for ContentsEntry {
+    type Error = ParseError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let (path, packages) = value
+            .trim_end()
+            .rsplit_once(char::is_whitespace)
+            .ok_or(ParseError::InvalidContentsEntry)?;
+        let path = path.trim();
+        if path.is_empty() {
+            return Err(ParseError::InvalidContentsEntry);
+        }
+
+        Ok(Self {
+            path: path.trim_start_matches('/').to_owned(),
+            packages: packages
+                .split(',')
+                .map(ContentsPackage::try_from)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+/// Iterator over the entries of a `Contents` file
+#[derive(Debug)]
+pub struct ContentsEntries<R> {
+    reader: R,
+    buffer: String,
+}
+
+impl<R> Iterator for ContentsEntries<R>
+where
+    R: BufRead,
+{
+    type Item = Result<ContentsEntry, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.buffer.clear();
+            match self.reader.read_line(&mut self.buffer) {
+                Ok(0) => return None,
+                Ok(_) => {
+                    let line = self.buffer.trim();
+                    // skip empty lines and the header of old Contents files
+                    if line.is_empty() || is_header(line) {
+                        continue;
+                    }
+                    return Some(ContentsEntry::try_from(line).map_err(Error::from));
+                }
+                Err(err) => return Some(Err(err.into())),
+            }
+        }
+    }
+}
+
+fn is_header(line: &str) -> bool {
+    let mut split = line.split_whitespace();
+    split.next() == Some("FILE") && split.next() == Some("LOCATION") && split.next().is_none()
+}
+
+/// Iterate over the entries of a reader
+pub fn from_reader<R>(reader: R) -> ContentsEntries<R>
+where
+    R: BufRead,
+{
+    ContentsEntries {
+        reader,
+        buffer: String::new(),
+    }
+}
+
+/// Iterate over the entries of a file
+///
+/// Files ending in `.xz` or `.gz` are decompressed while reading.
+pub fn from_file(path: impl AsRef<Path>) -> io::Result<ContentsEntries<Box<dyn BufRead + Send>>> {
+    Ok(from_reader(stanzas::open_file(path)?))
+}
+
+/// Index of a `Contents` file
+///
+/// The index maps paths to the packages shipping them and packages to the paths they ship.
+/// Sections are not kept in the index.
+#[derive(Clone, Debug, Default)]
+pub struct ContentsIndex {
+    files: ContentsMap,
+    packages: HashMap<String, BTreeSet<String>>,
+}
+
+impl ContentsIndex {
+    /// Build an index from entries
+    pub fn from_entries<I>(entries: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = Result<ContentsEntry, Error>>,
+    {
+        let mut index = Self::default();
+        for entry in entries {
+            index.insert(entry?);
+        }
+        Ok(index)
+    }
+
+    /// Add an entry to the index
+    pub fn insert(&mut self, entry: ContentsEntry) {
+        for package in entry.package_names() {
+            self.packages
+                .entry(package.to_owned())
+                .or_default()
+                .insert(entry.path.clone());
+        }
+        self.files
+            .entry(entry.path)
+            .or_default()
+            .extend(entry.packages.into_iter().map(|package| package.package));
+    }
+
+    /// Return the packages shipping a path
+    pub fn packages_for_path(&self, path: &str) -> Option<&Packages> {
+        self.files.get(path.trim_start_matches('/'))
+    }
+
+    /// Return the paths shipped by a package
+    pub fn paths_for_package(&self, package: &str) -> Option<&BTreeSet<String>> {
+        self.packages.get(package)
+    }
+
+    /// Iterate over all paths and the packages shipping them
+    pub fn files(&self) -> impl Iterator<Item = (&String, &Packages)> {
+        self.files.iter()
+    }
+
+    /// Convert the index into a map from paths to packages
+    pub fn into_contents_map(self) -> ContentsMap {
+        self.files
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Write;
+
+    use super::{from_file, from_reader, ContentsEntry, ContentsIndex, ContentsPackage};
+
+    const DATA: &str = r#"FILE                                                    LOCATION
+bin/sh                                                  shells/dash
+usr/share/doc/foo/file with spaces.txt                  doc/foo
+usr/lib/x86_64-linux-gnu/libfoo.so.1                    libs/libfoo1,non-free/libs/libfoo1-extra
+
+usr/share/doc/foo/copyright                             doc/foo
+"#;
+
+    const UDEB_DATA: &str = r#"lib/x86_64-linux-gnu/libc.so.6                          debian-installer/libc6-udeb
+usr/bin/udpkg                                           debian-installer/udpkg
+"#;
+
+    #[test]
+    fn entry() {
+        let entry = ContentsEntry::try_from(
+            "usr/share/doc/foo/file with spaces.txt     doc/foo,non-free/doc/bar",
+        )
+        .unwrap();
+        assert_eq!(entry.path, "usr/share/doc/foo/file with spaces.txt");
+        assert_eq!(
+            entry.packages,
+            vec![
+                ContentsPackage {
+                    section: Some("doc".into()),
+                    package: "foo".into()
+                },
+                ContentsPackage {
+                    section: Some("non-free/doc".into()),
+                    package: "bar".into()
+                },
+            ]
+        );
+        assert_eq!(entry.packages[1].to_string(), "non-free/doc/bar");
+        assert_eq!(
+            entry.package_names().collect::<Vec<_>>(),
+            vec!["foo", "bar"]
+        );
+
+        assert!(ContentsEntry::try_from("usr/bin/foo").is_err());
+        assert!(ContentsEntry::try_from("usr/bin/foo utils/").is_err());
+        assert!(ContentsEntry::try_from("usr/bin/foo utils/foo,").is_err());
+    }
+
+    #[test]
+    fn entries() {
+        let entries: Vec<_> = from_reader(DATA.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(entries.len(), 4);
+        assert_eq!(entries[0].path, "bin/sh");
+        assert_eq!(entries[1].path, "usr/share/doc/foo/file with spaces.txt");
+
+        let entries: Vec<_> = from_reader(UDEB_DATA.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            entries[0].packages[0].section.as_deref(),
+            Some("debian-installer")
+        );
+        assert_eq!(entries[0].packages[0].package, "libc6-udeb");
+    }
+
+    #[test]
+    fn index() {
+        let index = ContentsIndex::from_entries(from_reader(DATA.as_bytes())).unwrap();
+        assert!(index.packages_for_path("/bin/sh").unwrap().contains("dash"));
+        assert_eq!(
+            index
+                .packages_for_path("usr/lib/x86_64-linux-gnu/libfoo.so.1")
+                .unwrap()
+                .len(),
+            2
+        );
+        assert_eq!(index.paths_for_package("foo").unwrap().len(), 2);
+        assert!(index.paths_for_package("bar").is_none());
+        assert_eq!(index.files().count(), 4);
+        assert_eq!(index.into_contents_map().len(), 4);
+    }
+
+    #[test]
+    fn compressed() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("Contents-amd64.gz");
+        let mut encoder = flate2::write::GzEncoder::new(
+            std::fs::File::create(&path).unwrap(),
+            flate2::Compression::default(),
+        );
+        encoder.write_all(DATA.as_bytes()).unwrap();
+        encoder.finish().unwrap();
+
+        assert_eq!(from_file(&path).unwrap().count(), 4);
+    }
+}
@@ -0,0 +1,577 @@
+// Copyright 2022 Sebastian Ramacher
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! # Helpers to compare `Contents` indices
+//!
+//! This module compares the files of two `Contents` indices, e.g., of the same architecture in
+//! two suites, and reports files that were added, removed, moved to other packages, or moved
+//! between directories that are aliased on merged-/usr systems (like `/bin` and `/usr/bin`).
+//! Additionally, [aliased_collisions] finds files in a single index that are shipped at both of
+//! their aliased paths by different packages.
+//!
+//! Large indices can be processed piecewise with [Partition]s, which keep every path together
+//! with its aliased path.
+//!
+//! ```
+//! use assorted_debian_utils::contents_diff::{self, Change, ContentsMap};
+//!
+//! let old = ContentsMap::from([
+//!     ("bin/sh".into(), ["dash".into()].into()),
+//!     ("usr/bin/foo".into(), ["foo".into()].into()),
+//! ]);
+//! let new = ContentsMap::from([
+//!     ("usr/bin/sh".into(), ["dash".into()].into()),
+//!     ("usr/bin/foo".into(), ["foo-tools".into()].into()),
+//! ]);
+//!
+//! let changes = contents_diff::diff(&old, &new);
+//! assert_eq!(changes.len(), 2);
+//! assert!(matches!(&changes[0], Change::MovedBetweenDirectories { old_path, .. } if old_path == "bin/sh"));
+//! assert!(matches!(&changes[1], Change::MovedBetweenPackages { path, .. } if path == "usr/bin/foo"));
+//! ```
+
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeSet, HashMap},
+    fmt::{self, Display, Formatter},
+    hash::{Hash, Hasher},
+};
+
+/// Directories that are aliased between `/` and `/usr` on merged-/usr systems
+pub const ALIASED_DIRECTORIES: [&str; 7] = [
+    "bin/", "sbin/", "lib/", "lib32/", "lib64/", "libo32/", "libx32/",
+];
+
+/// Packages shipping a file
+pub type Packages = BTreeSet<String>;
+
+/// Map from paths (without leading slash) to the packages shipping them
+pub type ContentsMap = HashMap<String, Packages>;
+
+/// Check if a path is located in one of the aliased directories
+///
+/// Paths are expected without leading slash as found in `Contents` files.
+pub fn is_aliased(path: &str) -> bool {
+    let path = path.strip_prefix("usr/").unwrap_or(path);
+    ALIASED_DIRECTORIES
+        .into_iter()
+        .any(|directory| path.starts_with(directory))
+}
+
+/// Return the path that refers to the same file on merged-/usr systems
+///
+/// For paths in `/usr`, this is the path in `/` and vice versa. Paths outside of the aliased
+/// directories have no alias.
+pub fn aliased_path(path: &str) -> Option<String> {
+    if !is_aliased(path) {
+        return None;
+    }
+    Some(match path.strip_prefix("usr/") {
+        Some(stripped) => stripped.to_owned(),
+        None => format!("usr/{}", path),
+    })
+}
+
+/// A partition of the paths of `Contents` indices
+///
+/// Paths are assigned to partitions by their path without `/usr` prefix, so a path and its
+/// aliased path always end up in the same partition. Hence, comparing two indices partition by
+/// partition reports the same changes as comparing them at once, but only one partition needs to
+/// be kept in memory at a time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Partition {
+    index: u64,
+    count: u64,
+}
+
+impl Partition {
+    /// Split all paths into `count` partitions
+    pub fn split(count: u64) -> impl Iterator<Item = Self> {
+        let count = count.max(1);
+        (0..count).map(move |index| Self { index, count })
+    }
+
+    /// Check if a path belongs to the partition
+    pub fn contains(&self, path: &str) -> bool {
+        if self.count == 1 {
+            return true;
+        }
+        let mut hasher = DefaultHasher::new();
+        path.strip_prefix("usr/").unwrap_or(path).hash(&mut hasher);
+        hasher.finish() % self.count == self.index
+    }
+}
+
+impl Display for Partition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.index + 1, self.count)
+    }
+}
+
+/// A change of a file between two `Contents` indices
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Change {
+    /// The file is only shipped in the new index
+    Added {
+        /// Path of the file
+        path: String,
+        /// Packages shipping the file
+        packages: Packages,
+    },
+    /// The file is only shipped in the old index
+    Removed {
+        /// Path of the file
+        path: String,
+        /// Packages shipping the file
+        packages: Packages,
+    },
+    /// The file is shipped by different packages
+    MovedBetweenPackages {
+        /// Path of the file
+        path: String,
+        /// Packages shipping the file in the old index
+        old_packages: Packages,
+        /// Packages shipping the file in the new index
+        new_packages: Packages,
+    },
+    /// The file moved between aliased directories, e.g., from `/bin` to `/usr/bin`
+    ///
+    /// The packages shipping the file may have changed as well.
+    MovedBetweenDirectories {
+        /// Path of the file in the old index
+        old_path: String,
+        /// Path of the file in the new index
+        new_path: String,
+        /// Packages shipping the file in the old index
+        old_packages: Packages,
+        /// Packages shipping the file in the new index
+        new_packages: Packages,
+    },
+}
+
+impl Change {
+    /// Path of the file in the old index, or in the new index if it was added
+    pub fn path(&self) -> &str {
+        match self {
+            Change::Added { path, .. }
+            | Change::Removed { path, .. }
+            | Change::MovedBetweenPackages { path, .. }
+            | Change::MovedBetweenDirectories { old_path: path, .. } => path,
+        }
+    }
+
+    /// Iterate over all packages involved in the change
+    pub fn packages(&self) -> impl Iterator<Item = &String> {
+        let (old_packages, new_packages) = match self {
+            Change::Added { packages, .. } => (None, Some(packages)),
+            Change::Removed { packages, .. } => (Some(packages), None),
+            Change::MovedBetweenPackages {
+                old_packages,
+                new_packages,
+                ..
+            }
+            | Change::MovedBetweenDirectories {
+                old_packages,
+                new_packages,
+                ..
+            } => (Some(old_packages), Some(new_packages)),
+        };
+        old_packages
+            .into_iter()
+            .chain(new_packages)
+            .flat_map(|packages| packages.iter())
+    }
+}
+
+fn format_packages(packages: &Packages) -> String {
+    packages
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Added { path, packages } => {
+                write!(f, "added {}: {}", path, format_packages(packages))
+            }
+            Change::Removed { path, packages } => {
+                write!(f, "removed {}: {}", path, format_packages(packages))
+            }
+            Change::MovedBetweenPackages {
+                path,
+                old_packages,
+                new_packages,
+            } => write!(
+                f,
+                "moved {}: {} => {}",
+                path,
+                format_packages(old_packages),
+                format_packages(new_packages)
+            ),
+            Change::MovedBetweenDirectories {
+                old_path,
+                new_path,
+                old_packages,
+                new_packages,
+            } => write!(
+                f,
+                "moved {} => {}: {} => {}",
+                old_path,
+                new_path,
+                format_packages(old_packages),
+                format_packages(new_packages)
+            ),
+        }
+    }
+}
+
+/// Compare two `Contents` indices
+///
+/// A file that is no longer shipped at its old path, but at its aliased path (and was not
+/// shipped there before), is reported as moved between directories. Files shipped by the same
+/// packages in both indices are not reported. The changes are sorted by path.
+pub fn diff(old: &ContentsMap, new: &ContentsMap) -> Vec<Change> {
+    diff_with(old, new, aliased_path)
+}
+
+/// Compare two `Contents` indices with a custom mapping of paths to their aliased paths
+///
+/// This works like [diff], but `alias` decides which path a file may have moved to, e.g., to
+/// also consider moves between `/` and `/usr` outside of the aliased directories. To compare
+/// indices piecewise with [Partition]s, `alias` may only add or remove the `usr/` prefix.
+pub fn diff_with<F>(old: &ContentsMap, new: &ContentsMap, alias: F) -> Vec<Change>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut changes = Vec::new();
+    for (path, old_packages) in old {
+        if let Some(new_packages) = new.get(path) {
+            if old_packages != new_packages {
+                changes.push(Change::MovedBetweenPackages {
+                    path: path.clone(),
+                    old_packages: old_packages.clone(),
+                    new_packages: new_packages.clone(),
+                });
+            }
+            continue;
+        }
+
+        match alias(path).and_then(|alias| {
+            if old.contains_key(&alias) {
+                None
+            } else {
+                new.get(&alias).map(|packages| (alias, packages))
+            }
+        }) {
+            Some((new_path, new_packages)) => changes.push(Change::MovedBetweenDirectories {
+                old_path: path.clone(),
+                new_path,
+                old_packages: old_packages.clone(),
+                new_packages: new_packages.clone(),
+            }),
+            None => changes.push(Change::Removed {
+                path: path.clone(),
+                packages: old_packages.clone(),
+            }),
+        }
+    }
+
+    for (path, new_packages) in new {
+        if old.contains_key(path) {
+            continue;
+        }
+        // already reported as moved between directories
+        if let Some(alias) = alias(path) {
+            if old.contains_key(&alias) && !new.contains_key(&alias) {
+                continue;
+            }
+        }
+        changes.push(Change::Added {
+            path: path.clone(),
+            packages: new_packages.clone(),
+        });
+    }
+
+    changes.sort_by(|lhs, rhs| lhs.path().cmp(rhs.path()));
+    changes
+}
+
+/// A file shipped at both of its aliased paths
+///
+/// On merged-/usr systems, both paths refer to the same file. If different packages ship them,
+/// dpkg does not notice the conflict and removing or upgrading one of the packages may silently
+/// delete the file of the other one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AliasedCollision {
+    /// Path of the file in `/`
+    pub path: String,
+    /// Path of the file in `/usr`
+    pub aliased_path: String,
+    /// Packages shipping the file in `/`
+    pub packages: Packages,
+    /// Packages shipping the file in `/usr`
+    pub aliased_packages: Packages,
+}
+
+impl Display for AliasedCollision {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} vs {}: {} vs {}",
+            self.path,
+            self.aliased_path,
+            format_packages(&self.packages),
+            format_packages(&self.aliased_packages)
+        )
+    }
+}
+
+/// Find files that are shipped at both of their aliased paths by different packages
+///
+/// A file shipped at both paths by only one package is not reported. The collisions are sorted
+/// by path.
+pub fn aliased_collisions(contents: &ContentsMap) -> Vec<AliasedCollision> {
+    let mut collisions: Vec<AliasedCollision> = contents
+        .iter()
+        .filter(|(path, _)| !path.starts_with("usr/"))
+        .filter_map(|(path, packages)| {
+            let alias = aliased_path(path)?;
+            let aliased_packages = contents.get(&alias)?;
+            // a single package shipping both paths does not collide with itself
+            if packages.len() + aliased_packages.len() == 2 && packages == aliased_packages {
+                return None;
+            }
+            Some(AliasedCollision {
+                path: path.clone(),
+                aliased_path: alias,
+                packages: packages.clone(),
+                aliased_packages: aliased_packages.clone(),
+            })
+        })
+        .collect();
+    collisions.sort_by(|lhs, rhs| lhs.path.cmp(&rhs.path));
+    collisions
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        aliased_collisions, aliased_path, diff, diff_with, is_aliased, AliasedCollision, Change,
+        ContentsMap, Packages, Partition,
+    };
+
+    fn packages(packages: &[&str]) -> Packages {
+        packages.iter().map(|package| package.to_string()).collect()
+    }
+
+    fn contents(entries: &[(&str, &[&str])]) -> ContentsMap {
+        entries
+            .iter()
+            .map(|(path, p)| (path.to_string(), packages(p)))
+            .collect()
+    }
+
+    #[test]
+    fn aliased() {
+        assert!(is_aliased("bin/sh"));
+        assert!(is_aliased("usr/lib/x86_64-linux-gnu/libc.so.6"));
+        assert!(!is_aliased("usr/share/doc/foo"));
+        assert!(!is_aliased("libexec/foo"));
+
+        assert_eq!(aliased_path("bin/sh"), Some("usr/bin/sh".into()));
+        assert_eq!(aliased_path("usr/sbin/foo"), Some("sbin/foo".into()));
+        assert_eq!(aliased_path("usr/share/foo"), None);
+    }
+
+    #[test]
+    fn changes() {
+        let old = contents(&[
+            ("bin/sh", &["dash"]),
+            ("lib/libfoo.so", &["libfoo"]),
+            ("usr/bin/removed", &["foo"]),
+            ("usr/bin/moved", &["foo"]),
+            ("usr/share/doc/foo", &["foo"]),
+            ("sbin/both", &["foo"]),
+            ("usr/sbin/both", &["foo"]),
+        ]);
+        let new = contents(&[
+            ("usr/bin/sh", &["dash"]),
+            ("usr/lib/libfoo.so", &["libfoo2"]),
+            ("usr/bin/moved", &["bar"]),
+            ("usr/bin/added", &["bar"]),
+            ("usr/share/doc/foo", &["foo"]),
+            ("usr/sbin/both", &["foo"]),
+        ]);
+
+        assert_eq!(
+            diff(&old, &new),
+            vec![
+                Change::MovedBetweenDirectories {
+                    old_path: "bin/sh".into(),
+                    new_path: "usr/bin/sh".into(),
+                    old_packages: packages(&["dash"]),
+                    new_packages: packages(&["dash"]),
+                },
+                Change::MovedBetweenDirectories {
+                    old_path: "lib/libfoo.so".into(),
+                    new_path: "usr/lib/libfoo.so".into(),
+                    old_packages: packages(&["libfoo"]),
+                    new_packages: packages(&["libfoo2"]),
+                },
+                Change::Removed {
+                    path: "sbin/both".into(),
+                    packages: packages(&["foo"]),
+                },
+                Change::Added {
+                    path: "usr/bin/added".into(),
+                    packages: packages(&["bar"]),
+                },
+                Change::MovedBetweenPackages {
+                    path: "usr/bin/moved".into(),
+                    old_packages: packages(&["foo"]),
+                    new_packages: packages(&["bar"]),
+                },
+                Change::Removed {
+                    path: "usr/bin/removed".into(),
+                    packages: packages(&["foo"]),
+                },
+            ]
+        );
+        assert!(diff(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn changes_with_alias() {
+        let old = contents(&[("share/foo", &["foo"]), ("bin/sh", &["dash"])]);
+        let new = contents(&[("usr/share/foo", &["foo"]), ("usr/bin/sh", &["dash"])]);
+
+        // share/ is not aliased by default
+        assert_eq!(diff(&old, &new).len(), 3);
+        assert_eq!(
+            diff_with(&old, &new, |path| Some(match path.strip_prefix("usr/") {
+                Some(stripped) => stripped.to_owned(),
+                None => format!("usr/{}", path),
+            })),
+            vec![
+                Change::MovedBetweenDirectories {
+                    old_path: "bin/sh".into(),
+                    new_path: "usr/bin/sh".into(),
+                    old_packages: packages(&["dash"]),
+                    new_packages: packages(&["dash"]),
+                },
+                Change::MovedBetweenDirectories {
+                    old_path: "share/foo".into(),
+                    new_path: "usr/share/foo".into(),
+                    old_packages: packages(&["foo"]),
+                    new_packages: packages(&["foo"]),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn display() {
+        let change = Change::MovedBetweenDirectories {
+            old_path: "bin/sh".into(),
+            new_path: "usr/bin/sh".into(),
+            old_packages: packages(&["dash"]),
+            new_packages: packages(&["bash", "dash"]),
+        };
+        assert_eq!(
+            change.to_string(),
+            "moved bin/sh => usr/bin/sh: dash => bash,dash"
+        );
+        assert_eq!(
+            change.packages().collect::<Vec<_>>(),
+            vec!["dash", "bash", "dash"]
+        );
+    }
+
+    #[test]
+    fn collisions() {
+        let contents = contents(&[
+            ("bin/sh", &["dash"]),
+            ("usr/bin/sh", &["bash"]),
+            ("sbin/foo", &["foo"]),
+            ("usr/sbin/foo", &["foo"]),
+            ("lib/libbar.so", &["bar", "baz"]),
+            ("usr/lib/libbar.so", &["bar", "baz"]),
+            ("usr/lib/libfoo.so", &["foo"]),
+            ("usr/share/doc/foo", &["foo"]),
+        ]);
+
+        assert_eq!(
+            aliased_collisions(&contents),
+            vec![
+                AliasedCollision {
+                    path: "bin/sh".into(),
+                    aliased_path: "usr/bin/sh".into(),
+                    packages: packages(&["dash"]),
+                    aliased_packages: packages(&["bash"]),
+                },
+                AliasedCollision {
+                    path: "lib/libbar.so".into(),
+                    aliased_path: "usr/lib/libbar.so".into(),
+                    packages: packages(&["bar", "baz"]),
+                    aliased_packages: packages(&["bar", "baz"]),
+                },
+            ]
+        );
+        assert_eq!(
+            aliased_collisions(&contents)[0].to_string(),
+            "bin/sh vs usr/bin/sh: dash vs bash"
+        );
+    }
+
+    #[test]
+    fn partitions() {
+        let old = contents(&[
+            ("bin/sh", &["dash"]),
+            ("lib/libfoo.so", &["libfoo"]),
+            ("usr/bin/removed", &["foo"]),
+            ("usr/bin/moved", &["foo"]),
+            ("usr/share/doc/foo", &["foo"]),
+        ]);
+        let new = contents(&[
+            ("usr/bin/sh", &["dash"]),
+            ("usr/lib/libfoo.so", &["libfoo2"]),
+            ("usr/bin/moved", &["bar"]),
+            ("usr/bin/added", &["bar"]),
+            ("usr/share/doc/foo", &["foo"]),
+        ]);
+        let partition = |contents: &ContentsMap, partition: &Partition| -> ContentsMap {
+            contents
+                .iter()
+                .filter(|(path, _)| partition.contains(path))
+                .map(|(path, packages)| (path.clone(), packages.clone()))
+                .collect()
+        };
+
+        assert_eq!(Partition::split(0).count(), 1);
+        let partitions: Vec<_> = Partition::split(3).collect();
+        for path in old.keys().chain(new.keys()) {
+            assert_eq!(
+                partitions
+                    .iter()
+                    .filter(|partition| partition.contains(path))
+                    .count(),
+                1
+            );
+        }
+        for path in ["bin/sh", "lib/libfoo.so"] {
+            let alias = aliased_path(path).unwrap();
+            assert!(partitions
+                .iter()
+                .all(|partition| partition.contains(path) == partition.contains(&alias)));
+        }
+
+        let mut changes: Vec<_> = partitions
+            .iter()
+            .flat_map(|p| diff(&partition(&old, p), &partition(&new, p)))
+            .collect();
+        changes.sort_by(|lhs, rhs| lhs.path().cmp(rhs.path()));
+        assert_eq!(changes, diff(&old, &new));
+    }
+}
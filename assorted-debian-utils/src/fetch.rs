@@ -0,0 +1,1159 @@
+// Copyright 2022 Sebastian Ramacher
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! # Helpers to fetch data from Debian services
+//!
+//! This module provides an asynchronous layer to download `excuses.yaml`, `autoremovals.yaml`,
+//! `InRelease`, `Packages`, `Sources` and `Contents` files as well as arbitrary other files (e.g.,
+//! bug lists from UDD) into a [CacheStore], and to parse them into the typed structures of this
+//! crate. The HTTP backend is provided via the [HttpBackend] trait. Response bodies are streamed to
+//! disk and compressed files are stored decompressed. If a cached copy is available, conditional
+//! requests using its `ETag` and `Last-Modified` values are sent to the server. Cached copies are
+//! either kept in memory ([MemoryStore]) or in a directory ([DirectoryStore]).
+//!
+//! The module is only available with the `fetch` feature. With the `reqwest` feature, a backend
+//! based on [reqwest](https://docs.rs/reqwest) is provided as `ReqwestBackend`.
+
+use std::{
+    collections::HashMap,
+    fmt::{Debug, Display, Formatter},
+    fs::{self, File},
+    io::{self, BufRead, BufReader, BufWriter, Read, Seek, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tempfile::NamedTempFile;
+
+use crate::{
+    architectures::Architecture,
+    archive::{Checksum, Component, Suite},
+    autoremovals::{self, AutoRemovals},
+    contents::{self, ContentsEntries},
+    excuses::{self, Excuses},
+    release::{self, Release},
+    stanzas::{self, Stanzas},
+    udd_bugs::{self, UDDBugs, UDDBugsQuery},
+};
+
+/// Default mirror of the Debian archive
+pub const DEFAULT_MIRROR: &str = "https://deb.debian.org/debian";
+/// URL of `excuses.yaml`
+pub const EXCUSES_URL: &str = "https://release.debian.org/britney/excuses.yaml";
+/// URL of `autoremovals.yaml`
+pub const AUTOREMOVALS_URL: &str = "https://udd.debian.org/cgi-bin/autoremovals.yaml.cgi";
+/// Suffix of the files storing the [Metadata] of cached files
+pub const METADATA_SUFFIX: &str = ".metadata";
+
+// Number of attempts for downloads failing with transient errors
+const MAX_ATTEMPTS: u32 = 4;
+
+/// Error when fetching data
+#[derive(Debug)]
+pub enum Error {
+    /// The HTTP backend failed
+    Backend(Box<dyn std::error::Error + Send + Sync>),
+    /// The HTTP backend failed with an error that is worth retrying, e.g., a timeout
+    Transient(Box<dyn std::error::Error + Send + Sync>),
+    /// The server replied with an unexpected status code
+    Status(u16),
+    /// The downloaded data does not match the expected checksum
+    Checksum {
+        /// URL of the download
+        url: String,
+        /// Expected checksum
+        expected: Checksum,
+        /// SHA256 checksum of the downloaded data
+        sha256: String,
+        /// Size of the downloaded data
+        size: u64,
+    },
+    /// Reading, decompressing or storing data failed
+    Io(io::Error),
+    /// Parsing YAML data failed
+    Yaml(serde_yaml::Error),
+    /// Parsing RFC 822-style data failed
+    Rfc822(rfc822_like::de::Error),
+}
+
+impl Error {
+    /// Check whether the error is worth retrying, i.e., connection problems and server errors
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Error::Transient(_) => true,
+            Error::Status(status) => *status >= 500 || *status == 429,
+            _ => false,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Backend(err) | Error::Transient(err) => {
+                write!(f, "HTTP request failed: {}", err)
+            }
+            Error::Status(status) => write!(f, "unexpected HTTP status {}", status),
+            Error::Checksum {
+                url,
+                expected,
+                sha256,
+                size,
+            } => write!(
+                f,
+                "checksum mismatch for {}: expected {} ({} bytes), got {} ({} bytes)",
+                url, expected.checksum, expected.size, sha256, size
+            ),
+            Error::Io(err) => write!(f, "I/O error: {}", err),
+            Error::Yaml(err) => write!(f, "unable to parse YAML: {}", err),
+            Error::Rfc822(err) => write!(f, "unable to parse control data: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<serde_yaml::Error> for Error {
+    fn from(err: serde_yaml::Error) -> Self {
+        Error::Yaml(err)
+    }
+}
+
+impl From<rfc822_like::de::Error> for Error {
+    fn from(err: rfc822_like::de::Error) -> Self {
+        Error::Rfc822(err)
+    }
+}
+
+/// Result type
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// A `GET` request, possibly conditional on the cached copy
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Request {
+    /// URL to fetch
+    pub url: String,
+    /// `ETag` of the cached copy, sent as `If-None-Match`
+    pub etag: Option<String>,
+    /// `Last-Modified` of the cached copy, sent as `If-Modified-Since`
+    pub last_modified: Option<String>,
+    /// Offset to resume a partial download from, sent as `Range`
+    pub offset: Option<u64>,
+    /// Validator of the partial download, sent as `If-Range`
+    pub if_range: Option<String>,
+}
+
+/// Body of a response that is read chunk by chunk
+#[async_trait]
+pub trait Body: Send {
+    /// Return the next chunk of the body, or `None` once the body is exhausted
+    async fn chunk(&mut self) -> Result<Option<Vec<u8>>>;
+}
+
+/// Response of the HTTP backend
+pub enum Response {
+    /// The cached copy is still up to date
+    NotModified,
+    /// The server sent new data
+    Data {
+        /// Body of the response
+        body: Box<dyn Body>,
+        /// Length of the body if known
+        content_length: Option<u64>,
+        /// `ETag` reported by the server
+        etag: Option<String>,
+        /// `Last-Modified` reported by the server
+        last_modified: Option<String>,
+        /// The body continues the partial download at the requested offset
+        resumed: bool,
+    },
+}
+
+impl Debug for Response {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Response::NotModified => write!(f, "NotModified"),
+            Response::Data {
+                content_length,
+                etag,
+                last_modified,
+                resumed,
+                ..
+            } => f
+                .debug_struct("Data")
+                .field("content_length", content_length)
+                .field("etag", etag)
+                .field("last_modified", last_modified)
+                .field("resumed", resumed)
+                .finish_non_exhaustive(),
+        }
+    }
+}
+
+/// An HTTP backend
+///
+/// Implementations are expected to follow redirects and to report every status code other than
+/// `200`, `206` and `304` as error. Only `206` responses are reported as resumed.
+#[async_trait]
+pub trait HttpBackend: Send + Sync {
+    /// Perform a `GET` request
+    async fn get(&self, request: &Request) -> Result<Response>;
+
+    /// Wait before a failed request is retried
+    ///
+    /// The default implementation retries immediately.
+    async fn wait(&self, _duration: Duration) {}
+}
+
+/// Report the progress of downloads
+pub trait Progress: Sync {
+    /// A download of `length` bytes (if known) from `url` started
+    fn start(&self, _url: &str, _length: Option<u64>) {}
+    /// `bytes` more bytes were downloaded
+    fn advance(&self, _bytes: u64) {}
+    /// The download from `url` failed with `error` and is retried
+    fn retry(&self, _url: &str, _error: &Error) {}
+}
+
+/// Do not report any progress
+impl Progress for () {}
+
+/// State of a cached file after a download
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum State {
+    /// The cached copy is up to date
+    NotModified,
+    /// The file was downloaded
+    Downloaded,
+}
+
+/// A file to download into the cache directory
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Download {
+    /// URL to download from
+    pub url: String,
+    /// Name of the file in the cache directory
+    pub name: String,
+    /// Expected checksum of the data as sent by the server, i.e., before decompression
+    pub checksum: Option<Checksum>,
+}
+
+impl Download {
+    /// Create a new download from `url` to the file `name`
+    pub fn new(url: impl Into<String>, name: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            name: name.into(),
+            checksum: None,
+        }
+    }
+
+    /// Verify the downloaded data against a checksum
+    pub fn with_checksum(mut self, checksum: &Checksum) -> Self {
+        self.checksum = Some(checksum.clone());
+        self
+    }
+}
+
+/// Metadata of a cached file
+///
+/// The metadata is stored next to the cached file with the [METADATA_SUFFIX] suffix.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Metadata {
+    /// URL the file was downloaded from
+    pub url: String,
+    /// `ETag` reported by the server
+    #[serde(default)]
+    pub etag: Option<String>,
+    /// `Last-Modified` reported by the server
+    #[serde(default)]
+    pub last_modified: Option<String>,
+    /// SHA256 checksum of the downloaded data before decompression
+    #[serde(default)]
+    pub sha256: Option<String>,
+    /// Local time of the download
+    ///
+    /// The modification time of the cached file is set to the server's `Last-Modified` date, so
+    /// the age of a cached copy is determined from this value.
+    #[serde(default)]
+    pub downloaded: Option<DateTime<Utc>>,
+}
+
+impl Metadata {
+    /// Path of the file storing the metadata of a cached file
+    pub fn path(path: impl AsRef<Path>) -> PathBuf {
+        let mut path = path.as_ref().as_os_str().to_owned();
+        path.push(METADATA_SUFFIX);
+        PathBuf::from(path)
+    }
+
+    /// Load the metadata of a cached file
+    pub fn load(path: impl AsRef<Path>) -> Option<Self> {
+        let file = File::open(Self::path(path)).ok()?;
+        serde_yaml::from_reader(BufReader::new(file)).ok()
+    }
+
+    /// Store the metadata of a cached file
+    pub fn store(&self, path: impl AsRef<Path>) -> Result<()> {
+        let file = File::create(Self::path(path))?;
+        Ok(serde_yaml::to_writer(BufWriter::new(file), self)?)
+    }
+
+    /// Remove the metadata of a cached file
+    pub fn remove(path: impl AsRef<Path>) -> Result<()> {
+        match fs::remove_file(Self::path(path)) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// HTTP backend based on [reqwest](https://docs.rs/reqwest)
+#[cfg(feature = "reqwest")]
+#[derive(Debug, Default)]
+pub struct ReqwestBackend {
+    client: reqwest::Client,
+}
+
+#[cfg(feature = "reqwest")]
+impl ReqwestBackend {
+    /// Create a new backend from a client
+    pub fn new(client: reqwest::Client) -> Self {
+        Self { client }
+    }
+
+    fn map_error(err: reqwest::Error) -> Error {
+        if err.is_timeout() || err.is_connect() || err.is_body() {
+            Error::Transient(Box::new(err))
+        } else {
+            Error::Backend(Box::new(err))
+        }
+    }
+}
+
+#[cfg(feature = "reqwest")]
+#[async_trait]
+impl Body for reqwest::Response {
+    async fn chunk(&mut self) -> Result<Option<Vec<u8>>> {
+        Ok(reqwest::Response::chunk(self)
+            .await
+            .map_err(ReqwestBackend::map_error)?
+            .map(|chunk| chunk.to_vec()))
+    }
+}
+
+#[cfg(feature = "reqwest")]
+#[async_trait]
+impl HttpBackend for ReqwestBackend {
+    async fn get(&self, request: &Request) -> Result<Response> {
+        use reqwest::{header, StatusCode};
+
+        let mut builder = self.client.get(&request.url);
+        if let Some(etag) = &request.etag {
+            builder = builder.header(header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &request.last_modified {
+            builder = builder.header(header::IF_MODIFIED_SINCE, last_modified);
+        }
+        if let Some(offset) = request.offset {
+            builder = builder.header(header::RANGE, format!("bytes={}-", offset));
+            if let Some(if_range) = &request.if_range {
+                builder = builder.header(header::IF_RANGE, if_range);
+            }
+        }
+        let response = builder.send().await.map_err(Self::map_error)?;
+        let resumed = match response.status() {
+            StatusCode::NOT_MODIFIED => return Ok(Response::NotModified),
+            StatusCode::OK => false,
+            StatusCode::PARTIAL_CONTENT => true,
+            status => return Err(Error::Status(status.as_u16())),
+        };
+
+        let header_value = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value: &header::HeaderValue| value.to_str().ok())
+                .map(str::to_owned)
+        };
+        let etag = header_value(header::ETAG);
+        let last_modified = header_value(header::LAST_MODIFIED);
+        Ok(Response::Data {
+            content_length: response.content_length(),
+            body: Box::new(response),
+            etag,
+            last_modified,
+            resumed,
+        })
+    }
+
+    async fn wait(&self, duration: Duration) {
+        tokio::time::sleep(duration).await;
+    }
+}
+
+/// A store for cached copies
+///
+/// Entries are stored decompressed together with their [Metadata].
+pub trait CacheStore: Send + Sync {
+    /// Reader for the data of an entry
+    type Reader: BufRead + Send;
+
+    /// Load the metadata of the entry `name` if the entry exists
+    fn metadata(&self, name: &str) -> Option<Metadata>;
+
+    /// Open the data of the entry `name`
+    fn open(&self, name: &str) -> Result<Self::Reader>;
+
+    /// Store the data read from `data` with its metadata as entry `name`
+    fn store(&self, name: &str, data: &mut dyn Read, metadata: &Metadata) -> Result<()>;
+
+    /// Path to keep the data of an incomplete download of the entry `name`
+    ///
+    /// Interrupted downloads can only be resumed if the store provides such a path. The default
+    /// implementation does not.
+    fn partial_path(&self, _name: &str) -> Option<PathBuf> {
+        None
+    }
+}
+
+// An entry of a MemoryStore
+type MemoryEntry = (Metadata, Arc<[u8]>);
+
+/// A store that keeps all entries in memory
+#[derive(Debug, Default)]
+pub struct MemoryStore(Mutex<HashMap<String, MemoryEntry>>);
+
+impl CacheStore for MemoryStore {
+    type Reader = io::Cursor<Arc<[u8]>>;
+
+    fn metadata(&self, name: &str) -> Option<Metadata> {
+        self.0
+            .lock()
+            .unwrap()
+            .get(name)
+            .map(|(metadata, _)| metadata.clone())
+    }
+
+    fn open(&self, name: &str) -> Result<Self::Reader> {
+        match self.0.lock().unwrap().get(name) {
+            Some((_, data)) => Ok(io::Cursor::new(Arc::clone(data))),
+            None => Err(io::Error::from(io::ErrorKind::NotFound).into()),
+        }
+    }
+
+    fn store(&self, name: &str, data: &mut dyn Read, metadata: &Metadata) -> Result<()> {
+        let mut buffer = Vec::new();
+        data.read_to_end(&mut buffer)?;
+        self.0
+            .lock()
+            .unwrap()
+            .insert(name.to_owned(), (metadata.clone(), buffer.into()));
+        Ok(())
+    }
+}
+
+/// A store that keeps entries as files in a directory
+///
+/// The [Metadata] of each entry is stored next to it with the [METADATA_SUFFIX] suffix. The
+/// modification time of the files is set to the `Last-Modified` date reported by the server.
+#[derive(Debug)]
+pub struct DirectoryStore {
+    directory: PathBuf,
+}
+
+impl DirectoryStore {
+    /// Create a new store in the given directory
+    ///
+    /// The directory is created if it does not exist.
+    pub fn new(directory: impl Into<PathBuf>) -> Result<Self> {
+        let directory = directory.into();
+        fs::create_dir_all(&directory)?;
+        Ok(Self { directory })
+    }
+
+    /// Return the path of the entry `name`
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.directory.join(name)
+    }
+}
+
+impl CacheStore for DirectoryStore {
+    type Reader = BufReader<File>;
+
+    fn metadata(&self, name: &str) -> Option<Metadata> {
+        let path = self.path(name);
+        if !path.exists() {
+            return None;
+        }
+        Metadata::load(path)
+    }
+
+    fn open(&self, name: &str) -> Result<Self::Reader> {
+        Ok(BufReader::new(File::open(self.path(name))?))
+    }
+
+    fn store(&self, name: &str, data: &mut dyn Read, metadata: &Metadata) -> Result<()> {
+        let path = self.path(name);
+        let mut file = NamedTempFile::new_in(&self.directory)?;
+        let mut writer = BufWriter::new(file.as_file_mut());
+        io::copy(data, &mut writer)?;
+        writer.flush()?;
+        drop(writer);
+        // use the server's date as modification time to be independent of the local clock
+        if let Some(modified) = metadata
+            .last_modified
+            .as_deref()
+            .and_then(|last_modified| DateTime::parse_from_rfc2822(last_modified).ok())
+        {
+            file.as_file().set_modified(SystemTime::from(modified))?;
+        }
+
+        // the stored metadata is no longer valid for the new file
+        Metadata::remove(&path)?;
+        file.persist(&path).map_err(|err| err.error)?;
+        metadata.store(&path)
+    }
+
+    fn partial_path(&self, name: &str) -> Option<PathBuf> {
+        Some(self.directory.join(format!(".{}.partial", name)))
+    }
+}
+
+/// Decompress data based on the extension of the URL
+fn decompress<'a>(url: &str, input: &'a mut File) -> Box<dyn Read + 'a> {
+    if url.ends_with(".xz") {
+        Box::new(xz2::read::XzDecoder::new(BufReader::new(input)))
+    } else if url.ends_with(".gz") {
+        Box::new(flate2::read::GzDecoder::new(BufReader::new(input)))
+    } else {
+        Box::new(input)
+    }
+}
+
+/// Download files via an [HttpBackend] into a [CacheStore]
+///
+/// Compressed files are stored decompressed.
+#[derive(Debug)]
+pub struct Fetcher<B, S> {
+    backend: B,
+    store: S,
+    mirror: String,
+    always_download: bool,
+}
+
+impl<B, S> Fetcher<B, S>
+where
+    B: HttpBackend,
+    S: CacheStore,
+{
+    /// Create a new fetcher storing files in `store` and using [DEFAULT_MIRROR]
+    pub fn new(backend: B, store: S) -> Self {
+        Self {
+            backend,
+            store,
+            mirror: DEFAULT_MIRROR.to_string(),
+            always_download: false,
+        }
+    }
+
+    /// Use another mirror of the Debian archive
+    pub fn with_mirror(mut self, mirror: &str) -> Self {
+        self.mirror = mirror.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Always download files, i.e., never reuse cached copies
+    pub fn with_always_download(mut self, always_download: bool) -> Self {
+        self.always_download = always_download;
+        self
+    }
+
+    /// Return the URL of `file` in the `dists` directory of `suite` on the mirror
+    pub fn archive_url(&self, suite: Suite, file: &str) -> String {
+        format!("{}/dists/{}/{}", self.mirror, suite, file)
+    }
+
+    /// Return the store of the cached files
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    // Return the metadata and the size of a partial download if it can be resumed
+    //
+    // Partial downloads are resumed with `If-Range`, hence they require a validator.
+    fn partial_download(&self, download: &Download, path: &Path) -> Option<(String, u64)> {
+        let metadata = Metadata::load(path).filter(|metadata| metadata.url == download.url)?;
+        let validator = match metadata.etag {
+            // weak validators cannot be used with If-Range
+            Some(etag) if !etag.starts_with("W/") => etag,
+            _ => metadata.last_modified?,
+        };
+        let size = fs::metadata(path).ok()?.len();
+        if size == 0 {
+            return None;
+        }
+        Some((validator, size))
+    }
+
+    // Remove the data of a partial download
+    fn remove_partial(path: Option<&Path>) -> Result<()> {
+        let path = match path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        Metadata::remove(path)?;
+        match fs::remove_file(path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+
+    // Return the metadata of the cached copy if it can be reused for the download
+    fn cached_copy(&self, download: &Download, force: bool) -> Option<Metadata> {
+        if self.always_download || force {
+            return None;
+        }
+        // the metadata is only valid if the cached copy was downloaded from the same URL and
+        // matches the expected checksum
+        self.store.metadata(&download.name).filter(|metadata| {
+            metadata.url == download.url
+                && download.checksum.as_ref().map_or(true, |checksum| {
+                    metadata.sha256.as_deref() == Some(checksum.checksum.as_str())
+                })
+        })
+    }
+
+    async fn download_once(
+        &self,
+        download: &Download,
+        force: bool,
+        progress: &dyn Progress,
+    ) -> Result<State> {
+        let cached = self.cached_copy(download, force);
+        if cached.is_some() && download.checksum.is_some() {
+            // the cached copy matches the expected checksum
+            return Ok(State::NotModified);
+        }
+
+        let partial_path = self.store.partial_path(&download.name);
+        let partial = partial_path
+            .as_deref()
+            .and_then(|path| self.partial_download(download, path));
+        let mut request = Request {
+            url: download.url.clone(),
+            etag: cached.as_ref().and_then(|metadata| metadata.etag.clone()),
+            last_modified: cached
+                .as_ref()
+                .and_then(|metadata| metadata.last_modified.clone()),
+            offset: partial.as_ref().map(|(_, size)| *size),
+            if_range: partial.map(|(validator, _)| validator),
+        };
+        let response = match self.backend.get(&request).await {
+            // the partial download does not fit the current data, so start from scratch
+            Err(Error::Status(416)) if request.offset.is_some() => {
+                Self::remove_partial(partial_path.as_deref())?;
+                request.offset = None;
+                request.if_range = None;
+                self.backend.get(&request).await?
+            }
+            response => response?,
+        };
+        let (mut body, content_length, etag, last_modified, resumed) = match (response, cached) {
+            (Response::NotModified, Some(_)) => return Ok(State::NotModified),
+            // the server must not reply with 304 to an unconditional request
+            (Response::NotModified, None) => return Err(Error::Status(304)),
+            (
+                Response::Data {
+                    body,
+                    content_length,
+                    etag,
+                    last_modified,
+                    resumed,
+                },
+                _,
+            ) => (body, content_length, etag, last_modified, resumed),
+        };
+
+        // stream the data as received into the partial file, so that an interrupted download can
+        // be resumed later on
+        let mut hasher = Sha256::new();
+        let mut received = match partial_path.as_deref() {
+            Some(partial_path) if resumed && request.offset.is_some() => {
+                let mut received = fs::OpenOptions::new()
+                    .read(true)
+                    .append(true)
+                    .open(partial_path)?;
+                io::copy(&mut received, &mut hasher)?;
+                received
+            }
+            Some(partial_path) => {
+                // the server sent the complete data
+                Self::remove_partial(Some(partial_path))?;
+                let received = fs::OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .create_new(true)
+                    .open(partial_path)?;
+                Metadata {
+                    url: download.url.clone(),
+                    etag: etag.clone(),
+                    last_modified: last_modified.clone(),
+                    ..Default::default()
+                }
+                .store(partial_path)?;
+                received
+            }
+            // without a path for partial data, an interrupted download cannot be resumed
+            None => tempfile::tempfile()?,
+        };
+        let mut size = received.metadata()?.len();
+        progress.start(&download.url, content_length.map(|length| size + length));
+        progress.advance(size);
+        while let Some(chunk) = body.chunk().await? {
+            hasher.update(&chunk);
+            size += chunk.len() as u64;
+            received.write_all(&chunk)?;
+            progress.advance(chunk.len() as u64);
+        }
+        let sha256 = format!("{:x}", hasher.finalize());
+        if let Some(checksum) = &download.checksum {
+            if checksum.checksum != sha256 || checksum.size != size {
+                // the received data is of no use
+                Self::remove_partial(partial_path.as_deref())?;
+                return Err(Error::Checksum {
+                    url: download.url.clone(),
+                    expected: checksum.clone(),
+                    sha256,
+                    size,
+                });
+            }
+        }
+
+        received.rewind()?;
+        self.store.store(
+            &download.name,
+            &mut decompress(&download.url, &mut received),
+            &Metadata {
+                url: download.url.clone(),
+                etag,
+                last_modified,
+                sha256: Some(sha256),
+                downloaded: Some(Utc::now()),
+            },
+        )?;
+        drop(received);
+        Self::remove_partial(partial_path.as_deref())?;
+        Ok(State::Downloaded)
+    }
+
+    /// Download a file into the cache directory
+    ///
+    /// If the cached copy matches the expected checksum, no request is sent. Otherwise a
+    /// conditional request is sent if a cached copy is available. If `force` is set, cached
+    /// copies are never reused. Downloads failing with transient errors are retried with
+    /// exponential backoff. Interrupted downloads are resumed with `Range` requests if the server
+    /// supports them.
+    pub async fn download(
+        &self,
+        download: &Download,
+        force: bool,
+        progress: &dyn Progress,
+    ) -> Result<State> {
+        let mut attempt = 1;
+        loop {
+            match self.download_once(download, force, progress).await {
+                Err(err) if attempt < MAX_ATTEMPTS && err.is_transient() => {
+                    progress.retry(&download.url, &err);
+                    self.backend
+                        .wait(Duration::from_secs(1 << (attempt - 1)))
+                        .await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    // Download a file and open the cached copy
+    async fn open(&self, url: String, name: String) -> Result<S::Reader> {
+        let download = Download::new(url, name);
+        self.download(&download, false, &()).await?;
+        self.store.open(&download.name)
+    }
+
+    /// Fetch and parse `excuses.yaml`
+    pub async fn excuses(&self) -> Result<Excuses> {
+        let reader = self
+            .open(EXCUSES_URL.to_string(), "excuses.yaml".to_string())
+            .await?;
+        Ok(excuses::from_reader(reader)?)
+    }
+
+    /// Fetch and parse `autoremovals.yaml`
+    pub async fn autoremovals(&self) -> Result<AutoRemovals> {
+        let reader = self
+            .open(
+                AUTOREMOVALS_URL.to_string(),
+                "autoremovals.yaml".to_string(),
+            )
+            .await?;
+        Ok(autoremovals::from_reader(reader)?)
+    }
+
+    /// Fetch and parse the bugs matching a UDD query
+    ///
+    /// The results of each distinct query are cached separately.
+    pub async fn udd_bugs(&self, query: &UDDBugsQuery) -> Result<UDDBugs> {
+        let reader = self
+            .open(query.url(), format!("udd-bugs-{}.yaml", query.key()))
+            .await?;
+        Ok(udd_bugs::from_reader(reader)?)
+    }
+
+    /// Fetch and parse the `InRelease` file of a suite
+    ///
+    /// Note that the signature is not verified.
+    pub async fn release(&self, suite: Suite) -> Result<Release> {
+        let mut reader = self
+            .open(
+                self.archive_url(suite, "InRelease"),
+                format!("InRelease_{}", suite),
+            )
+            .await?;
+        let mut data = String::new();
+        io::Read::read_to_string(&mut reader, &mut data)?;
+        Ok(release::from_signed_str(&data)?)
+    }
+
+    /// Fetch a `Packages` file and iterate over its stanzas
+    ///
+    /// The stanzas are deserialized into `T`.
+    pub async fn packages<T>(
+        &self,
+        suite: Suite,
+        component: Component,
+        architecture: &Architecture,
+    ) -> Result<Stanzas<S::Reader, T>>
+    where
+        T: DeserializeOwned,
+    {
+        let reader = self
+            .open(
+                self.archive_url(
+                    suite,
+                    &format!("{}/binary-{}/Packages.xz", component, architecture),
+                ),
+                format!("Packages_{}_{}_{}", suite, component, architecture),
+            )
+            .await?;
+        Ok(stanzas::from_reader(reader))
+    }
+
+    /// Fetch a `Sources` file and iterate over its stanzas
+    ///
+    /// The stanzas are deserialized into `T`.
+    pub async fn sources<T>(
+        &self,
+        suite: Suite,
+        component: Component,
+    ) -> Result<Stanzas<S::Reader, T>>
+    where
+        T: DeserializeOwned,
+    {
+        let reader = self
+            .open(
+                self.archive_url(suite, &format!("{}/source/Sources.xz", component)),
+                format!("Sources_{}_{}", suite, component),
+            )
+            .await?;
+        Ok(stanzas::from_reader(reader))
+    }
+
+    /// Fetch a `Contents` file and iterate over its entries
+    pub async fn contents(
+        &self,
+        suite: Suite,
+        component: Component,
+        architecture: &Architecture,
+    ) -> Result<ContentsEntries<S::Reader>> {
+        let reader = self
+            .open(
+                self.archive_url(
+                    suite,
+                    &format!("{}/Contents-{}.gz", component, architecture),
+                ),
+                format!("Contents_{}_{}_{}", suite, component, architecture),
+            )
+            .await?;
+        Ok(contents::from_reader(reader))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io::Read,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Mutex,
+        },
+    };
+
+    use async_trait::async_trait;
+    use serde::Deserialize;
+    use sha2::{Digest, Sha256};
+
+    use super::{
+        Body, CacheStore, DirectoryStore, Download, Error, Fetcher, HttpBackend, MemoryStore,
+        Metadata, Request, Response, Result, State,
+    };
+    use crate::archive::{Checksum, Component, Suite};
+
+    /// Body sending its data in one chunk, optionally failing afterwards
+    struct TestBody {
+        data: Option<Vec<u8>>,
+        interrupted: bool,
+    }
+
+    #[async_trait]
+    impl Body for TestBody {
+        async fn chunk(&mut self) -> Result<Option<Vec<u8>>> {
+            match self.data.take() {
+                Some(data) => Ok(Some(data)),
+                None if self.interrupted => Err(Error::Transient("connection reset".into())),
+                None => Ok(None),
+            }
+        }
+    }
+
+    /// Backend serving a fixed document and recording all requests
+    #[derive(Default)]
+    struct TestBackend {
+        etag: &'static str,
+        data: Vec<u8>,
+        // number of requests failing with a server error before the document is served
+        failures: AtomicUsize,
+        // number of bytes served before the first complete response is interrupted
+        interrupt_at: Option<usize>,
+        requests: Mutex<Vec<Request>>,
+    }
+
+    #[async_trait]
+    impl HttpBackend for TestBackend {
+        async fn get(&self, request: &Request) -> Result<Response> {
+            self.requests.lock().unwrap().push(request.clone());
+            if self
+                .failures
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |failures| {
+                    failures.checked_sub(1)
+                })
+                .is_ok()
+            {
+                Err(Error::Status(503))
+            } else if request.etag.as_deref() == Some(self.etag) {
+                Ok(Response::NotModified)
+            } else {
+                let offset = match (request.offset, request.if_range.as_deref()) {
+                    (Some(offset), Some(if_range)) if if_range == self.etag => offset as usize,
+                    _ => 0,
+                };
+                let interrupt_at = self
+                    .interrupt_at
+                    .filter(|_| offset == 0 && self.requests.lock().unwrap().len() == 1);
+                let data = &self.data[offset..interrupt_at.unwrap_or(self.data.len())];
+                Ok(Response::Data {
+                    body: Box::new(TestBody {
+                        data: Some(data.to_vec()),
+                        interrupted: interrupt_at.is_some(),
+                    }),
+                    content_length: Some((self.data.len() - offset) as u64),
+                    etag: Some(self.etag.to_string()),
+                    last_modified: Some("Sat, 01 Oct 2022 08:00:00 GMT".to_string()),
+                    resumed: offset != 0,
+                })
+            }
+        }
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Eq)]
+    #[serde(rename_all = "PascalCase")]
+    struct Package {
+        package: String,
+        version: String,
+    }
+
+    #[tokio::test]
+    async fn conditional_requests() {
+        let mut data = Vec::new();
+        xz2::read::XzEncoder::new(
+            b"Package: foo\nVersion: 1.0\n\nPackage: bar\nVersion: 2.0\n".as_ref(),
+            6,
+        )
+        .read_to_end(&mut data)
+        .unwrap();
+        let backend = TestBackend {
+            etag: "\"1234\"",
+            data,
+            ..Default::default()
+        };
+        let directory = tempfile::tempdir().unwrap();
+        let fetcher = Fetcher::new(backend, DirectoryStore::new(directory.path()).unwrap())
+            .with_mirror("http://mirror/");
+
+        for _ in 0..2 {
+            let packages = fetcher
+                .sources::<Package>(Suite::Unstable, Component::Main)
+                .await
+                .unwrap()
+                .collect::<std::result::Result<Vec<_>, _>>()
+                .unwrap();
+            assert_eq!(packages.len(), 2);
+            assert_eq!(packages[1].package, "bar");
+        }
+
+        let requests = fetcher.backend.requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(
+            requests[0].url,
+            "http://mirror/dists/unstable/main/source/Sources.xz"
+        );
+        assert_eq!(requests[0].etag, None);
+        assert_eq!(requests[1].etag.as_deref(), Some("\"1234\""));
+        assert_eq!(
+            requests[1].last_modified.as_deref(),
+            Some("Sat, 01 Oct 2022 08:00:00 GMT")
+        );
+
+        let metadata = Metadata::load(fetcher.store().path("Sources_unstable_main")).unwrap();
+        assert_eq!(metadata.etag.as_deref(), Some("\"1234\""));
+        assert!(metadata.downloaded.is_some());
+    }
+
+    #[tokio::test]
+    async fn checksum() {
+        let backend = TestBackend {
+            etag: "\"5678\"",
+            data: b"foo".to_vec(),
+            failures: AtomicUsize::new(2),
+            ..Default::default()
+        };
+        let directory = tempfile::tempdir().unwrap();
+        let fetcher = Fetcher::new(backend, DirectoryStore::new(directory.path()).unwrap());
+
+        let mismatch = Download::new("http://mirror/foo", "foo").with_checksum(&Checksum {
+            checksum: format!("{:x}", Sha256::digest(b"bar")),
+            size: 3,
+            filename: "foo".into(),
+        });
+        // the server errors are retried before the checksum mismatch is detected
+        assert!(matches!(
+            fetcher.download(&mismatch, false, &()).await,
+            Err(Error::Checksum { .. })
+        ));
+        assert_eq!(fetcher.backend.requests.lock().unwrap().len(), 3);
+        assert!(!fetcher.store().path("foo").exists());
+
+        let download = Download::new("http://mirror/foo", "foo").with_checksum(&Checksum {
+            checksum: format!("{:x}", Sha256::digest(b"foo")),
+            size: 3,
+            filename: "foo".into(),
+        });
+        assert_eq!(
+            fetcher.download(&download, false, &()).await.unwrap(),
+            State::Downloaded
+        );
+        assert_eq!(std::fs::read(fetcher.store().path("foo")).unwrap(), b"foo");
+        // the cached copy matches the checksum, so no request is sent
+        assert_eq!(
+            fetcher.download(&download, false, &()).await.unwrap(),
+            State::NotModified
+        );
+        assert_eq!(fetcher.backend.requests.lock().unwrap().len(), 4);
+        // unless the download is forced
+        assert_eq!(
+            fetcher.download(&download, true, &()).await.unwrap(),
+            State::Downloaded
+        );
+    }
+
+    #[tokio::test]
+    async fn resume() {
+        let backend = TestBackend {
+            etag: "\"abcd\"",
+            data: b"foobar".to_vec(),
+            interrupt_at: Some(3),
+            ..Default::default()
+        };
+        let directory = tempfile::tempdir().unwrap();
+        let fetcher = Fetcher::new(backend, DirectoryStore::new(directory.path()).unwrap());
+
+        let download = Download::new("http://mirror/foobar", "foobar").with_checksum(&Checksum {
+            checksum: format!("{:x}", Sha256::digest(b"foobar")),
+            size: 6,
+            filename: "foobar".into(),
+        });
+        assert_eq!(
+            fetcher.download(&download, false, &()).await.unwrap(),
+            State::Downloaded
+        );
+        assert_eq!(
+            std::fs::read(fetcher.store().path("foobar")).unwrap(),
+            b"foobar"
+        );
+        assert!(!fetcher.store().partial_path("foobar").unwrap().exists());
+
+        // the interrupted download is resumed after the received data
+        let requests = fetcher.backend.requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].offset, None);
+        assert_eq!(requests[1].offset, Some(3));
+        assert_eq!(requests[1].if_range.as_deref(), Some("\"abcd\""));
+    }
+
+    #[tokio::test]
+    async fn memory_store() {
+        let backend = TestBackend {
+            etag: "\"ef01\"",
+            data: b"foobar".to_vec(),
+            interrupt_at: Some(3),
+            ..Default::default()
+        };
+        let fetcher = Fetcher::new(backend, MemoryStore::default());
+
+        let download = Download::new("http://mirror/foobar", "foobar");
+        // interrupted downloads cannot be resumed without a path for the partial data
+        assert!(matches!(
+            fetcher.download(&download, false, &()).await,
+            Ok(State::Downloaded)
+        ));
+        assert_eq!(
+            fetcher.download(&download, false, &()).await.unwrap(),
+            State::NotModified
+        );
+        let mut data = Vec::new();
+        fetcher
+            .store()
+            .open("foobar")
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        assert_eq!(data, b"foobar");
+
+        let requests = fetcher.backend.requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[1].offset, None);
+        assert_eq!(requests[2].etag.as_deref(), Some("\"ef01\""));
+    }
+}
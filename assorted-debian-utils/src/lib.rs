@@ -7,7 +7,15 @@
 //! * [architectures]: Helpers to handle Debian architectures
 //! * [archive]: Helpers for various features of the Debian archive
 //! * [buildinfo]: Helpers to handle `.buildinfo` files
+//! * [contents]: Helpers to handle `Contents` files
+//! * [contents_diff]: Helpers to compare `Contents` indices
 //! * [excuses]: Helpers to handle `excuses.yaml` for testing migration
+//! * `fetch`: Helpers to fetch data from Debian services (requires the `fetch` feature)
+//! * [outdated_built_using]: Helpers to handle `outdated-built-using.txt`
+//! * [relations]: Helpers to handle package relations
+//! * [release]: Helpers to handle `Release` files
+//! * [stanzas]: Helpers to iterate over stanzas of RFC 822-style files
+//! * `udd_bugs`: Helpers to handle bug lists from UDD (requires the `fetch` feature)
 //! * [version]: Helpers to handle package versions
 //! * [wb]: Helpers to generate commands for wanna-build
 
@@ -23,7 +31,17 @@ pub mod architectures;
 pub mod archive;
 pub mod autoremovals;
 pub mod buildinfo;
+pub mod contents;
+pub mod contents_diff;
 pub mod excuses;
+#[cfg(feature = "fetch")]
+pub mod fetch;
+pub mod outdated_built_using;
+pub mod relations;
+pub mod release;
+pub mod stanzas;
+#[cfg(feature = "fetch")]
+pub mod udd_bugs;
 mod utils;
 pub mod version;
 pub mod wb;
@@ -50,6 +68,14 @@ pub enum ParseError {
     InvalidMultiArch,
     /// Given string is not a valid compoment
     InvalidComponent,
+    /// Given string is not a valid outdated Built-Using entry
+    InvalidOutdatedBuiltUsing,
+    /// Given string is not a valid package relation
+    InvalidRelation,
+    /// Given string is not a valid checksum entry
+    InvalidChecksum,
+    /// Given string is not a valid Contents entry
+    InvalidContentsEntry,
 }
 
 impl Display for ParseError {
@@ -65,6 +91,12 @@ impl Display for ParseError {
             ParseError::InvalidSuiteOrCodename => write!(f, "invalid suite or codename"),
             ParseError::InvalidMultiArch => write!(f, "invalid multi-arch"),
             ParseError::InvalidComponent => write!(f, "invalid component"),
+            ParseError::InvalidOutdatedBuiltUsing => {
+                write!(f, "invalid outdated Built-Using entry")
+            }
+            ParseError::InvalidRelation => write!(f, "invalid relation"),
+            ParseError::InvalidChecksum => write!(f, "invalid checksum"),
+            ParseError::InvalidContentsEntry => write!(f, "invalid Contents entry"),
         }
     }
 }
@@ -0,0 +1,203 @@
+// Copyright 2022 Sebastian Ramacher
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! # Helpers to handle `outdated-built-using.txt`
+//!
+//! This module provides helpers to parse [outdated-built-using.txt](https://ftp-master.debian.org/users/ansgar/outdated-built-using.txt),
+//! the list of packages whose `Built-Using` field refers to a source package version that is no
+//! longer available in the archive. The report is a table with the columns `suite`, `source`,
+//! `version`, `bu_source` and `bu_version`.
+
+use std::{
+    fmt::{Display, Formatter},
+    io::{self, BufRead},
+};
+
+use crate::{archive::Suite, version::PackageVersion, ParseError};
+
+/// Error when reading outdated Built-Using reports
+#[derive(Debug)]
+pub enum Error {
+    /// Reading from the underlying reader failed
+    Io(io::Error),
+    /// Parsing a line failed
+    Parse(ParseError),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "unable to read outdated Built-Using entry: {}", err),
+            Error::Parse(err) => write!(f, "unable to parse outdated Built-Using entry: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(err: ParseError) -> Self {
+        Error::Parse(err)
+    }
+}
+
+/// An entry of the outdated Built-Using report
+///
+/// The report is generated per source package and has no column with architectures. The
+/// affected binary packages and their architectures need to be looked up in the `Packages` files
+/// of the suite.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OutdatedBuiltUsing {
+    /// Suite of the package
+    pub suite: Suite,
+    /// Source package name
+    pub source: String,
+    /// Version of the source package
+    pub version: PackageVersion,
+    /// Source package embedded via Built-Using
+    pub built_using_source: String,
+    /// Outdated version of the embedded source package
+    pub built_using_version: PackageVersion,
+}
+
+impl TryFrom<&str> for OutdatedBuiltUsing {
+    type Error = ParseError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let split: Vec<&str> = value.split('|').map(str::trim).collect();
+        if split.len() != 5 || split.iter().any(|column| column.is_empty()) {
+            return Err(ParseError::InvalidOutdatedBuiltUsing);
+        }
+
+        Ok(Self {
+            suite: split[0].try_into()?,
+            source: split[1].to_owned(),
+            version: split[2].try_into()?,
+            built_using_source: split[3].to_owned(),
+            built_using_version: split[4].try_into()?,
+        })
+    }
+}
+
+// Check if the line is part of the table layout, i.e., the header, the separator below the
+// header, or the row count at the end
+fn is_layout(line: &str) -> bool {
+    let line = line.trim();
+    line.is_empty()
+        || line.chars().all(|c| c == '-' || c == '+')
+        || line.split('|').next().map(str::trim) == Some("suite")
+        || (line.starts_with('(') && (line.ends_with(" rows)") || line.ends_with(" row)")))
+}
+
+/// Iterator over the entries of an outdated Built-Using report
+#[derive(Debug)]
+pub struct OutdatedBuiltUsingEntries<R> {
+    lines: io::Lines<R>,
+}
+
+impl<R> Iterator for OutdatedBuiltUsingEntries<R>
+where
+    R: BufRead,
+{
+    type Item = Result<OutdatedBuiltUsing, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.lines.next()? {
+                Ok(line) => {
+                    if is_layout(&line) {
+                        continue;
+                    }
+                    return Some(OutdatedBuiltUsing::try_from(line.as_str()).map_err(Error::from));
+                }
+                Err(err) => return Some(Err(err.into())),
+            }
+        }
+    }
+}
+
+/// Iterate over the outdated Built-Using entries of a reader
+pub fn from_reader<R>(reader: R) -> OutdatedBuiltUsingEntries<R>
+where
+    R: BufRead,
+{
+    OutdatedBuiltUsingEntries {
+        lines: reader.lines(),
+    }
+}
+
+/// Read outdated Built-Using entries from a string
+pub fn from_str(data: &str) -> Result<Vec<OutdatedBuiltUsing>, ParseError> {
+    data.lines()
+        .filter(|line| !is_layout(line))
+        .map(OutdatedBuiltUsing::try_from)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{from_reader, from_str, Error, OutdatedBuiltUsing};
+    use crate::{archive::Suite, version::PackageVersion};
+
+    const TEST_DATA: &str = r#"    suite     |        source         |   version    |  bu_source   | bu_version
+--------------+-----------------------+--------------+--------------+------------
+ unstable     | rust-sequoia-sq       | 0.25.0-6     | rust-clap    | 2.34.0-2
+ testing      | rust-sequoia-sq       | 0.25.0-6     | rust-clap    | 2.34.0-2
+ unstable     | ghc-cabal             | 3.4.1.0-1    | ghc          | 9.0.2-1
+(3 rows)
+
+"#;
+
+    #[test]
+    fn parse() {
+        let entries = from_str(TEST_DATA).unwrap();
+        assert_eq!(entries.len(), 3);
+
+        assert_eq!(entries[0].suite, Suite::Unstable);
+        assert_eq!(entries[0].source, "rust-sequoia-sq");
+        assert_eq!(
+            entries[0].version,
+            PackageVersion::try_from("0.25.0-6").unwrap()
+        );
+        assert_eq!(entries[0].built_using_source, "rust-clap");
+        assert_eq!(
+            entries[0].built_using_version,
+            PackageVersion::try_from("2.34.0-2").unwrap()
+        );
+
+        assert_eq!(entries[1].suite, Suite::Testing(None));
+        assert_eq!(entries[2].source, "ghc-cabal");
+    }
+
+    #[test]
+    fn reader() {
+        let entries = from_reader(TEST_DATA.as_bytes())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(entries, from_str(TEST_DATA).unwrap());
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(OutdatedBuiltUsing::try_from("unstable | foo | 1.0-1").is_err());
+        assert!(OutdatedBuiltUsing::try_from("foo | bar | 1.0-1 | baz | 1.0-1").is_err());
+        assert!(OutdatedBuiltUsing::try_from("unstable | bar | 1.0-1 | baz | :1.0").is_err());
+        assert!(
+            OutdatedBuiltUsing::try_from("unstable | bar | 1.0-1 | baz | 1.0-1 | amd64").is_err()
+        );
+        assert!(OutdatedBuiltUsing::try_from("unstable | bar | 1.0-1 |  | 1.0-1").is_err());
+
+        let data = " unstable | foo | 1.0-1 | bar | 1.0-1\n garbage\n";
+        assert!(from_str(data).is_err());
+        let entries: Vec<_> = from_reader(data.as_bytes()).collect();
+        assert_eq!(entries.len(), 2);
+        assert!(entries[0].is_ok());
+        assert!(matches!(entries[1], Err(Error::Parse(_))));
+    }
+}
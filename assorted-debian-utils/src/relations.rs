@@ -0,0 +1,334 @@
+// Copyright 2022 Sebastian Ramacher
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! # Helpers to handle package relations
+//!
+//! This module provides types to represent package relations as found in `Depends`, `Breaks`,
+//! `Replaces`, `Installed-Build-Depends` and similar fields.
+//!
+//! ```
+//! use assorted_debian_utils::relations::{Relation, VersionOperator};
+//!
+//! let relation = Relation::try_from("libc6 (>= 2.34)").expect("Failed to parse relation");
+//! assert_eq!(relation.package, "libc6");
+//! let constraint = relation.version_constraint.expect("No version constraint");
+//! assert_eq!(constraint.operator, VersionOperator::LaterOrEqual);
+//! assert_eq!(constraint.version.to_string(), "2.34");
+//! ```
+
+use std::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+use serde::{de, Deserialize, Serialize};
+
+use crate::{architectures::Architecture, version::PackageVersion, ParseError};
+
+/// Operators of versioned relations
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum VersionOperator {
+    /// `<<`
+    StrictlyEarlier,
+    /// `<=`
+    EarlierOrEqual,
+    /// `=`
+    Exactly,
+    /// `>=`
+    LaterOrEqual,
+    /// `>>`
+    StrictlyLater,
+}
+
+impl Display for VersionOperator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            VersionOperator::StrictlyEarlier => write!(f, "<<"),
+            VersionOperator::EarlierOrEqual => write!(f, "<="),
+            VersionOperator::Exactly => write!(f, "="),
+            VersionOperator::LaterOrEqual => write!(f, ">="),
+            VersionOperator::StrictlyLater => write!(f, ">>"),
+        }
+    }
+}
+
+impl TryFrom<&str> for VersionOperator {
+    type Error = ParseError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "<<" => Ok(VersionOperator::StrictlyEarlier),
+            "<=" => Ok(VersionOperator::EarlierOrEqual),
+            "=" => Ok(VersionOperator::Exactly),
+            ">=" => Ok(VersionOperator::LaterOrEqual),
+            ">>" => Ok(VersionOperator::StrictlyLater),
+            _ => Err(ParseError::InvalidRelation),
+        }
+    }
+}
+
+impl FromStr for VersionOperator {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        VersionOperator::try_from(s)
+    }
+}
+
+/// A version constraint of a relation, e.g., `(>= 1.0-1)`
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct VersionConstraint {
+    /// The operator
+    pub operator: VersionOperator,
+    /// The version
+    pub version: PackageVersion,
+}
+
+impl VersionConstraint {
+    /// Check whether the given version satisfies the constraint.
+    pub fn is_satisfied_by(&self, version: &PackageVersion) -> bool {
+        match self.operator {
+            VersionOperator::StrictlyEarlier => version < &self.version,
+            VersionOperator::EarlierOrEqual => version <= &self.version,
+            VersionOperator::Exactly => version == &self.version,
+            VersionOperator::LaterOrEqual => version >= &self.version,
+            VersionOperator::StrictlyLater => version > &self.version,
+        }
+    }
+}
+
+impl Display for VersionConstraint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.operator, self.version)
+    }
+}
+
+impl TryFrom<&str> for VersionConstraint {
+    type Error = ParseError;
+
+    /// Parse a version constraint with or without the surrounding parentheses, e.g., `>= 1.0-1`.
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let value = value.trim();
+        let value = match value.strip_prefix('(') {
+            Some(stripped) => stripped
+                .strip_suffix(')')
+                .ok_or(ParseError::InvalidRelation)?
+                .trim(),
+            None => value,
+        };
+        // the operator is followed by the version, but the whitespace in between is optional
+        let split = value
+            .find(|c: char| !"<=>".contains(c))
+            .ok_or(ParseError::InvalidRelation)?;
+        let (operator, version) = value.split_at(split);
+        Ok(Self {
+            operator: operator.try_into()?,
+            version: version.trim().try_into()?,
+        })
+    }
+}
+
+impl FromStr for VersionConstraint {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        VersionConstraint::try_from(s)
+    }
+}
+
+/// Architecture qualifier of a relation, e.g., `python3:any` or `libc6:amd64`
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ArchitectureQualifier {
+    /// The `:any` qualifier
+    Any,
+    /// The `:native` qualifier
+    Native,
+    /// A specific architecture
+    Architecture(Architecture),
+}
+
+impl Display for ArchitectureQualifier {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ArchitectureQualifier::Any => write!(f, "any"),
+            ArchitectureQualifier::Native => write!(f, "native"),
+            ArchitectureQualifier::Architecture(arch) => write!(f, "{}", arch),
+        }
+    }
+}
+
+impl TryFrom<&str> for ArchitectureQualifier {
+    type Error = ParseError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "any" => Ok(ArchitectureQualifier::Any),
+            "native" => Ok(ArchitectureQualifier::Native),
+            _ => Ok(ArchitectureQualifier::Architecture(value.try_into()?)),
+        }
+    }
+}
+
+/// A single package relation
+///
+/// Alternatives (`|`) and architecture or build profile restrictions are not handled by this
+/// type.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Relation {
+    /// Name of the package
+    pub package: String,
+    /// Optional architecture qualifier
+    pub architecture: Option<ArchitectureQualifier>,
+    /// Optional version constraint
+    pub version_constraint: Option<VersionConstraint>,
+}
+
+impl Display for Relation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.package)?;
+        if let Some(architecture) = &self.architecture {
+            write!(f, ":{}", architecture)?;
+        }
+        if let Some(version_constraint) = &self.version_constraint {
+            write!(f, " ({})", version_constraint)?;
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<&str> for Relation {
+    type Error = ParseError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let value = value.trim();
+        let (package, version_constraint) = match value.find('(') {
+            Some(index) => {
+                let (package, constraint) = value.split_at(index);
+                (package.trim(), Some(constraint.try_into()?))
+            }
+            None => (value, None),
+        };
+
+        let (package, architecture) = match package.split_once(':') {
+            Some((package, architecture)) => (package, Some(architecture.try_into()?)),
+            None => (package, None),
+        };
+        if package.is_empty() || package.contains(char::is_whitespace) {
+            return Err(ParseError::InvalidRelation);
+        }
+
+        Ok(Self {
+            package: package.to_owned(),
+            architecture,
+            version_constraint,
+        })
+    }
+}
+
+impl FromStr for Relation {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Relation::try_from(s)
+    }
+}
+
+impl Serialize for Relation {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Relation {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct RelationVisitor;
+
+        impl<'de> de::Visitor<'de> for RelationVisitor {
+            type Value = Relation;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                write!(formatter, "a package relation")
+            }
+
+            fn visit_str<E>(self, s: &str) -> Result<Self::Value, E>
+            where
+                E: de::Error,
+            {
+                Relation::try_from(s)
+                    .map_err(|_| de::Error::invalid_value(de::Unexpected::Str(s), &self))
+            }
+        }
+
+        deserializer.deserialize_str(RelationVisitor)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ArchitectureQualifier, Relation, VersionConstraint, VersionOperator};
+    use crate::{architectures::Architecture, version::PackageVersion};
+
+    #[test]
+    fn from_str() {
+        let relation = Relation::try_from("autoconf").unwrap();
+        assert_eq!(relation.package, "autoconf");
+        assert!(relation.architecture.is_none());
+        assert!(relation.version_constraint.is_none());
+
+        let relation = Relation::try_from("automake (= 1:1.16.5-1.1)").unwrap();
+        assert_eq!(relation.package, "automake");
+        let constraint = relation.version_constraint.unwrap();
+        assert_eq!(constraint.operator, VersionOperator::Exactly);
+        assert_eq!(
+            constraint.version,
+            PackageVersion::try_from("1:1.16.5-1.1").unwrap()
+        );
+
+        let relation = Relation::try_from("libc6:amd64 (<<2.34)").unwrap();
+        assert_eq!(relation.package, "libc6");
+        assert_eq!(
+            relation.architecture,
+            Some(ArchitectureQualifier::Architecture(Architecture::Amd64))
+        );
+        assert_eq!(
+            relation.version_constraint.unwrap().operator,
+            VersionOperator::StrictlyEarlier
+        );
+
+        let relation = Relation::try_from("python3:any").unwrap();
+        assert_eq!(relation.architecture, Some(ArchitectureQualifier::Any));
+    }
+
+    #[test]
+    fn constraint() {
+        let constraint = VersionConstraint::try_from(">= 12.2.0-3").unwrap();
+        assert!(constraint.is_satisfied_by(&PackageVersion::try_from("12.2.0-3").unwrap()));
+        assert!(constraint.is_satisfied_by(&PackageVersion::try_from("12.2.0-14").unwrap()));
+        assert!(!constraint.is_satisfied_by(&PackageVersion::try_from("12.1.0-8").unwrap()));
+
+        let constraint = VersionConstraint::try_from("(<< 12.2.0-5)").unwrap();
+        assert!(constraint.is_satisfied_by(&PackageVersion::try_from("12.2.0-3").unwrap()));
+        assert!(!constraint.is_satisfied_by(&PackageVersion::try_from("12.2.0-5").unwrap()));
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(Relation::try_from("").is_err());
+        assert!(Relation::try_from("foo (= 1.0").is_err());
+        assert!(Relation::try_from("foo (~ 1.0)").is_err());
+        assert!(Relation::try_from("foo bar").is_err());
+    }
+
+    #[test]
+    fn display() {
+        for relation in ["foo", "foo:any", "foo (>= 1.0-1)", "foo:i386 (<< 2:1.0)"] {
+            assert_eq!(Relation::try_from(relation).unwrap().to_string(), relation);
+        }
+    }
+}
@@ -0,0 +1,475 @@
+// Copyright 2022 Sebastian Ramacher
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! # Helpers to handle `Release` files
+//!
+//! This module provides `Release` to represent the fields of a `dists/<suite>/Release` file, and
+//! `SuiteMapping` to resolve suites to codenames (and vice versa) based on the contents of the
+//! release files. Without any release files, the mapping falls back to the codenames known to
+//! [Codename].
+//!
+//! ```
+//! use assorted_debian_utils::{archive::Suite, release::{self, SuiteMapping}};
+//!
+//! let release = release::from_str(r#"Origin: Debian
+//! Label: Debian
+//! Suite: testing
+//! Codename: trixie
+//! Date: Sat, 10 Jun 2023 20:13:18 UTC
+//! Architectures: amd64 arm64
+//! Components: main contrib non-free-firmware non-free
+//! "#).expect("Failed to parse release file");
+//! assert_eq!(release.suite, Suite::Testing(None));
+//!
+//! let mapping = SuiteMapping::from_releases([&release]);
+//! assert_eq!(mapping.codename_for_suite(Suite::Testing(None)).as_deref(), Some("trixie"));
+//! assert_eq!(mapping.suite_for_codename("trixie"), Some(Suite::Testing(None)));
+//! ```
+
+use std::{collections::HashMap, fmt, io::BufRead};
+
+use chrono::{DateTime, Utc};
+use serde::{
+    de::{self, Visitor},
+    Deserialize, Deserializer,
+};
+
+use crate::{
+    architectures::Architecture,
+    archive::{Checksum, Codename, Component, Extension, Suite, SuiteOrCodename},
+    utils::{
+        deserialize_checksums, deserialize_optional_rfc2822_datetime, deserialize_rfc2822_datetime,
+    },
+};
+
+fn deserialize_suite<'de, D>(deserializer: D) -> Result<Suite, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct SuiteVisitor;
+
+    impl<'de> Visitor<'de> for SuiteVisitor {
+        type Value = Suite;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a suite")
+        }
+
+        fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            Suite::try_from(v).map_err(|_| E::invalid_value(de::Unexpected::Str(v), &self))
+        }
+    }
+
+    deserializer.deserialize_str(SuiteVisitor)
+}
+
+fn deserialize_architectures<'de, D>(deserializer: D) -> Result<Vec<Architecture>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct ArchitecturesVisitor;
+
+    impl<'de> Visitor<'de> for ArchitecturesVisitor {
+        type Value = Vec<Architecture>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a list of architectures")
+        }
+
+        fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            // invalid architecture names are ignored
+            Ok(v.split_whitespace()
+                .filter_map(|arch| arch.try_into().ok())
+                .collect())
+        }
+    }
+
+    deserializer.deserialize_str(ArchitecturesVisitor)
+}
+
+fn deserialize_components<'de, D>(deserializer: D) -> Result<Vec<Component>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct ComponentsVisitor;
+
+    impl<'de> Visitor<'de> for ComponentsVisitor {
+        type Value = Vec<Component>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a list of components")
+        }
+
+        fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+        where
+            E: de::Error,
+        {
+            v.split_whitespace()
+                // the security archive used to prefix the components with updates/
+                .map(|component| component.strip_prefix("updates/").unwrap_or(component))
+                .map(|component| {
+                    Component::try_from(component)
+                        .map_err(|_| E::custom(format!("invalid component: {}", component)))
+                })
+                .collect()
+        }
+    }
+
+    deserializer.deserialize_str(ComponentsVisitor)
+}
+
+/// A release file
+#[derive(Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Release {
+    /// Origin of the archive
+    pub origin: Option<String>,
+    /// Label of the archive
+    pub label: Option<String>,
+    /// Suite
+    #[serde(deserialize_with = "deserialize_suite")]
+    pub suite: Suite,
+    /// Codename of the suite
+    pub codename: String,
+    /// Version of the release
+    pub version: Option<String>,
+    /// Creation date of the release file
+    #[serde(deserialize_with = "deserialize_rfc2822_datetime")]
+    pub date: DateTime<Utc>,
+    /// Date until which the release file is valid
+    #[serde(
+        rename = "Valid-Until",
+        default,
+        deserialize_with = "deserialize_optional_rfc2822_datetime"
+    )]
+    pub valid_until: Option<DateTime<Utc>>,
+    /// Architectures of the suite
+    #[serde(default, deserialize_with = "deserialize_architectures")]
+    pub architectures: Vec<Architecture>,
+    /// Components of the suite
+    #[serde(default, deserialize_with = "deserialize_components")]
+    pub components: Vec<Component>,
+    /// MD5 checksums of the index files
+    #[serde(rename = "MD5Sum", default, deserialize_with = "deserialize_checksums")]
+    pub md5sum: Vec<Checksum>,
+    /// SHA256 checksums of the index files
+    #[serde(rename = "SHA256", default, deserialize_with = "deserialize_checksums")]
+    pub sha256: Vec<Checksum>,
+}
+
+impl Release {
+    /// Return the architectures of the suite without `all`.
+    pub fn binary_architectures(&self) -> Vec<Architecture> {
+        self.architectures
+            .iter()
+            .filter(|arch| **arch != Architecture::All && **arch != Architecture::Source)
+            .cloned()
+            .collect()
+    }
+
+    /// Return the SHA256 checksum of the given index file, e.g., `main/binary-amd64/Packages.xz`.
+    pub fn sha256_for(&self, filename: &str) -> Option<&Checksum> {
+        self.sha256
+            .iter()
+            .find(|checksum| checksum.filename == filename)
+    }
+}
+
+/// Read a release file from a reader
+pub fn from_reader(reader: impl BufRead) -> Result<Release, rfc822_like::de::Error> {
+    rfc822_like::from_reader(reader)
+}
+
+/// Read a release file from a string
+pub fn from_str(data: &str) -> Result<Release, rfc822_like::de::Error> {
+    rfc822_like::from_str(data)
+}
+
+/// Read a release file from an inline signed `InRelease` file
+///
+/// The signature is not verified. Data outside the signed message is ignored.
+pub fn from_signed_str(data: &str) -> Result<Release, rfc822_like::de::Error> {
+    if !data.starts_with("-----BEGIN PGP SIGNED MESSAGE-----") {
+        return from_str(data);
+    }
+
+    let mut message = String::with_capacity(data.len());
+    for line in data
+        .lines()
+        // skip the armor headers
+        .skip_while(|line| !line.is_empty())
+        .skip(1)
+        .take_while(|line| !line.starts_with("-----BEGIN PGP SIGNATURE-----"))
+    {
+        // undo dash-escaping
+        message.push_str(line.strip_prefix("- ").unwrap_or(line));
+        message.push('\n');
+    }
+    from_str(&message)
+}
+
+// Split a suite into the base suite and its extension
+fn split_suite(suite: Suite) -> (Suite, Option<Extension>) {
+    match suite {
+        Suite::Testing(ext) => (Suite::Testing(None), ext),
+        Suite::Stable(ext) => (Suite::Stable(None), ext),
+        Suite::OldStable(ext) => (Suite::OldStable(None), ext),
+        Suite::Unstable | Suite::Experimental => (suite, None),
+    }
+}
+
+// Combine a base suite with an extension
+fn with_extension(suite: Suite, extension: Option<Extension>) -> Option<Suite> {
+    match (suite, extension) {
+        (_, None) => Some(suite),
+        (Suite::Testing(None), ext) => Some(Suite::Testing(ext)),
+        (Suite::Stable(None), ext) => Some(Suite::Stable(ext)),
+        (Suite::OldStable(None), ext) => Some(Suite::OldStable(ext)),
+        _ => None,
+    }
+}
+
+/// Mapping between suites and codenames
+///
+/// The mapping is built from release files. The codenames of unstable (`sid`) and experimental
+/// (`rc-buggy`) are always known. If no release files were added, the mapping falls back to the
+/// built-in mapping of [Codename]. Once release files are available, the built-in mapping is not
+/// used as it might be outdated.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SuiteMapping {
+    codenames: HashMap<Suite, String>,
+    suites: HashMap<String, Suite>,
+}
+
+impl SuiteMapping {
+    /// Create a mapping from release files
+    pub fn from_releases<'a>(releases: impl IntoIterator<Item = &'a Release>) -> Self {
+        let mut mapping = Self::default();
+        for release in releases {
+            mapping.add_release(release);
+        }
+        mapping
+    }
+
+    /// Add the suite and codename of a release file to the mapping
+    pub fn add_release(&mut self, release: &Release) {
+        self.codenames
+            .insert(release.suite, release.codename.clone());
+        self.suites.insert(release.codename.clone(), release.suite);
+    }
+
+    /// Return the codename of a suite
+    pub fn codename_for_suite(&self, suite: Suite) -> Option<String> {
+        if let Some(codename) = self.codenames.get(&suite) {
+            return Some(codename.clone());
+        }
+        match split_suite(suite) {
+            (Suite::Unstable | Suite::Experimental, _) => Some(Codename::from(suite).to_string()),
+            _ if self.codenames.is_empty() => Some(Codename::from(suite).to_string()),
+            (base, Some(ext)) => self
+                .codenames
+                .get(&base)
+                .map(|codename| format!("{}-{}", codename, ext)),
+            _ => None,
+        }
+    }
+
+    /// Return the suite of a codename
+    pub fn suite_for_codename(&self, codename: &str) -> Option<Suite> {
+        if let Some(suite) = self.suites.get(codename) {
+            return Some(*suite);
+        }
+        match Codename::try_from(codename).ok()? {
+            Codename::Sid => Some(Suite::Unstable),
+            Codename::RCBuggy => Some(Suite::Experimental),
+            parsed if self.suites.is_empty() => Suite::try_from(parsed).ok(),
+            _ => {
+                let (base, extension) = codename.split_once('-')?;
+                let suite = self.suites.get(base)?;
+                with_extension(*suite, Some(Extension::try_from(extension).ok()?))
+            }
+        }
+    }
+
+    /// Resolve a suite or codename to a suite
+    pub fn resolve_suite(&self, suite: &SuiteOrCodename) -> Option<Suite> {
+        match suite {
+            SuiteOrCodename::Suite(suite) => Some(*suite),
+            SuiteOrCodename::Codename(codename) => self.suite_for_codename(&codename.to_string()),
+        }
+    }
+
+    /// Resolve a suite or codename to a codename
+    pub fn resolve_codename(&self, suite: &SuiteOrCodename) -> Option<String> {
+        match suite {
+            SuiteOrCodename::Suite(suite) => self.codename_for_suite(*suite),
+            SuiteOrCodename::Codename(codename) => Some(codename.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{TimeZone, Utc};
+
+    use super::{from_signed_str, from_str, SuiteMapping};
+    use crate::{
+        architectures::Architecture,
+        archive::{Codename, Component, Extension, Suite, SuiteOrCodename},
+    };
+
+    const RELEASE: &str = r#"Origin: Debian
+Label: Debian
+Suite: stable
+Version: 12.0
+Codename: bookworm
+Changelogs: https://metadata.ftp-master.debian.org/changelogs/@CHANGEPATH@_changelog
+Date: Sat, 10 Jun 2023 08:55:38 UTC
+Acquire-By-Hash: yes
+No-Support-for-Architecture-all: Packages
+Architectures: all amd64 arm64 armel armhf i386 mips64el mipsel ppc64el s390x
+Components: main contrib non-free-firmware non-free
+Description: Debian 12.0 Released 10 June 2023
+MD5Sum:
+ 0ed6d4c8891eb86358b94bb35d9e4da4  1484322 contrib/Contents-all
+ d0a0325a97c42fd5f66a8c3e29bcea64    98581 contrib/Contents-all.gz
+SHA256:
+ d6c9c82f4e61b4662f9ba16b9ebb379c57b4943f8b7813091d1f637325ddfb79  1484322 contrib/Contents-all
+ 3e9a121d599b56c08bc8f144e4830807c77c29d7114316d6984ba54695d3db7b    98581 contrib/Contents-all.gz
+"#;
+
+    #[test]
+    fn deserialize() {
+        let release = from_str(RELEASE).unwrap();
+        assert_eq!(release.origin.as_deref(), Some("Debian"));
+        assert_eq!(release.suite, Suite::Stable(None));
+        assert_eq!(release.codename, "bookworm");
+        assert_eq!(release.version.as_deref(), Some("12.0"));
+        assert_eq!(
+            release.date,
+            Utc.with_ymd_and_hms(2023, 6, 10, 8, 55, 38).unwrap()
+        );
+        assert!(release.valid_until.is_none());
+        assert_eq!(release.architectures.len(), 10);
+        assert_eq!(release.architectures[1], Architecture::Amd64);
+        assert_eq!(release.binary_architectures().len(), 9);
+        assert!(!release.binary_architectures().contains(&Architecture::All));
+        assert_eq!(
+            release.components,
+            vec![
+                Component::Main,
+                Component::Contrib,
+                Component::NonFreeFirmware,
+                Component::NonFree
+            ]
+        );
+        assert_eq!(release.md5sum.len(), 2);
+        assert_eq!(release.sha256.len(), 2);
+        let checksum = release.sha256_for("contrib/Contents-all.gz").unwrap();
+        assert_eq!(checksum.size, 98581);
+        assert!(release.sha256_for("main/Contents-all.gz").is_none());
+    }
+
+    #[test]
+    fn signed() {
+        let signed = format!(
+            "-----BEGIN PGP SIGNED MESSAGE-----\nHash: SHA512\n\n{}-----BEGIN PGP SIGNATURE-----\n\niQIzBAEBCgAdFiEE\n-----END PGP SIGNATURE-----\n",
+            RELEASE
+        );
+        assert_eq!(
+            from_signed_str(&signed).unwrap(),
+            from_str(RELEASE).unwrap()
+        );
+        assert_eq!(
+            from_signed_str(RELEASE).unwrap(),
+            from_str(RELEASE).unwrap()
+        );
+    }
+
+    #[test]
+    fn mapping() {
+        let stable = from_str(RELEASE).unwrap();
+        let testing = from_str(
+            &RELEASE
+                .replace("Suite: stable", "Suite: testing")
+                .replace("Codename: bookworm", "Codename: trixie"),
+        )
+        .unwrap();
+        let mapping = SuiteMapping::from_releases([&stable, &testing]);
+
+        assert_eq!(
+            mapping.codename_for_suite(Suite::Stable(None)).as_deref(),
+            Some("bookworm")
+        );
+        assert_eq!(
+            mapping.codename_for_suite(Suite::Testing(None)).as_deref(),
+            Some("trixie")
+        );
+        assert_eq!(
+            mapping
+                .codename_for_suite(Suite::Stable(Some(Extension::ProposedUpdates)))
+                .as_deref(),
+            Some("bookworm-proposed-updates")
+        );
+        // the codenames of unstable and experimental are fixed
+        assert_eq!(
+            mapping.codename_for_suite(Suite::Unstable).as_deref(),
+            Some("sid")
+        );
+        assert_eq!(
+            mapping.codename_for_suite(Suite::Experimental).as_deref(),
+            Some("rc-buggy")
+        );
+        // no release file for oldstable
+        assert_eq!(mapping.codename_for_suite(Suite::OldStable(None)), None);
+
+        assert_eq!(
+            mapping.suite_for_codename("trixie"),
+            Some(Suite::Testing(None))
+        );
+        assert_eq!(
+            mapping.suite_for_codename("bookworm-backports"),
+            Some(Suite::Stable(Some(Extension::Backports)))
+        );
+        assert_eq!(mapping.suite_for_codename("sid"), Some(Suite::Unstable));
+        assert_eq!(mapping.suite_for_codename("forky"), None);
+        assert_eq!(mapping.suite_for_codename("bullseye"), None);
+
+        assert_eq!(
+            mapping.resolve_suite(&SuiteOrCodename::Codename(Codename::Bookworm(None))),
+            Some(Suite::Stable(None))
+        );
+        assert_eq!(
+            mapping
+                .resolve_codename(&SuiteOrCodename::Suite(Suite::Testing(None)))
+                .as_deref(),
+            Some("trixie")
+        );
+    }
+    #[test]
+    fn fallback_mapping() {
+        let mapping = SuiteMapping::default();
+
+        assert_eq!(
+            mapping.codename_for_suite(Suite::Stable(None)).as_deref(),
+            Some("bullseye")
+        );
+        assert_eq!(
+            mapping
+                .codename_for_suite(Suite::Testing(Some(Extension::Backports)))
+                .as_deref(),
+            Some("bookworm-backports")
+        );
+        assert_eq!(
+            mapping.suite_for_codename("stretch"),
+            Some(Suite::OldStable(None))
+        );
+        assert_eq!(mapping.suite_for_codename("trixie"), None);
+    }
+}
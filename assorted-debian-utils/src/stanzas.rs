@@ -0,0 +1,221 @@
+// Copyright 2022 Sebastian Ramacher
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! # Helpers to iterate over stanzas of RFC 822-style files
+//!
+//! Files like `Packages` or `Sources` consist of many stanzas separated by empty lines. This module
+//! provides an iterator that deserializes one stanza at a time with [serde], so that only a single
+//! stanza needs to be kept in memory.
+//!
+//! ```
+//! use assorted_debian_utils::stanzas;
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! #[serde(rename_all = "PascalCase")]
+//! struct Package {
+//!     package: String,
+//!     version: String,
+//! }
+//!
+//! let data = "Package: foo\nVersion: 1.0\n\nPackage: bar\nVersion: 2.0\n";
+//! let packages: Vec<Package> = stanzas::from_reader(data.as_bytes())
+//!     .collect::<Result<_, _>>()
+//!     .unwrap();
+//! assert_eq!(packages.len(), 2);
+//! assert_eq!(packages[1].package, "bar");
+//! ```
+
+use std::{
+    fmt::{Display, Formatter},
+    fs::File,
+    io::{self, BufRead, BufReader},
+    marker::PhantomData,
+    path::Path,
+};
+
+use serde::de::DeserializeOwned;
+
+/// Error when reading stanzas
+#[derive(Debug)]
+pub enum Error {
+    /// Reading from the underlying reader failed
+    Io(io::Error),
+    /// Deserializing a stanza failed
+    Parse(rfc822_like::de::Error),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "unable to read stanza: {}", err),
+            Error::Parse(err) => write!(f, "unable to parse stanza: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<rfc822_like::de::Error> for Error {
+    fn from(err: rfc822_like::de::Error) -> Self {
+        Error::Parse(err)
+    }
+}
+
+/// Iterator over the stanzas of a reader
+///
+/// Each stanza is deserialized into `T`.
+#[derive(Debug)]
+pub struct Stanzas<R, T> {
+    reader: R,
+    buffer: String,
+    phantom: PhantomData<fn() -> T>,
+}
+
+impl<R, T> Iterator for Stanzas<R, T>
+where
+    R: BufRead,
+    T: DeserializeOwned,
+{
+    type Item = Result<T, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.buffer.clear();
+        loop {
+            let start = self.buffer.len();
+            match self.reader.read_line(&mut self.buffer) {
+                Ok(0) => break,
+                Ok(_) => {
+                    if self.buffer[start..].trim().is_empty() {
+                        self.buffer.truncate(start);
+                        // skip empty lines before the stanza
+                        if !self.buffer.is_empty() {
+                            break;
+                        }
+                    }
+                }
+                Err(err) => return Some(Err(err.into())),
+            }
+        }
+
+        if self.buffer.is_empty() {
+            None
+        } else {
+            Some(rfc822_like::from_str(&self.buffer).map_err(Error::from))
+        }
+    }
+}
+
+/// Iterate over the stanzas of a reader
+pub fn from_reader<R, T>(reader: R) -> Stanzas<R, T>
+where
+    R: BufRead,
+    T: DeserializeOwned,
+{
+    Stanzas {
+        reader,
+        buffer: String::new(),
+        phantom: PhantomData,
+    }
+}
+
+/// Open a file that is possibly compressed
+///
+/// Files ending in `.xz` or `.gz` are decompressed while reading.
+pub fn open_file(path: impl AsRef<Path>) -> io::Result<Box<dyn BufRead + Send>> {
+    let path = path.as_ref();
+    let file = File::open(path)?;
+    Ok(
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("xz") => Box::new(BufReader::new(xz2::read::XzDecoder::new(file))),
+            Some("gz") => Box::new(BufReader::new(flate2::read::GzDecoder::new(file))),
+            _ => Box::new(BufReader::new(file)),
+        },
+    )
+}
+
+/// Iterate over the stanzas of a file
+///
+/// Files ending in `.xz` or `.gz` are decompressed while reading.
+pub fn from_file<T>(path: impl AsRef<Path>) -> io::Result<Stanzas<Box<dyn BufRead + Send>, T>>
+where
+    T: DeserializeOwned,
+{
+    Ok(from_reader(open_file(path)?))
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Write;
+
+    use serde::Deserialize;
+
+    use super::{from_file, from_reader};
+
+    #[derive(Debug, Deserialize, PartialEq, Eq)]
+    #[serde(rename_all = "PascalCase")]
+    struct Package {
+        package: String,
+        version: String,
+        description: Option<String>,
+    }
+
+    const DATA: &str = r#"
+
+Package: foo
+Version: 1.0
+Description: short
+ long description
+ .
+ more
+
+Package: bar
+Version: 2.0
+
+
+Package: baz
+Version: 3.0"#;
+
+    #[test]
+    fn stanzas() {
+        let packages: Vec<Package> = from_reader(DATA.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(packages.len(), 3);
+        assert_eq!(packages[0].package, "foo");
+        assert!(packages[0]
+            .description
+            .as_ref()
+            .unwrap()
+            .contains("long description"));
+        assert_eq!(packages[1].description, None);
+        assert_eq!(packages[2].version, "3.0");
+
+        assert_eq!(from_reader::<_, Package>(&b"\n\n"[..]).count(), 0);
+        assert!(from_reader::<_, Package>(&b"Package: foo\n"[..])
+            .next()
+            .unwrap()
+            .is_err());
+    }
+
+    #[test]
+    fn compressed() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("Packages.gz");
+        let mut encoder = flate2::write::GzEncoder::new(
+            std::fs::File::create(&path).unwrap(),
+            flate2::Compression::default(),
+        );
+        encoder.write_all(DATA.as_bytes()).unwrap();
+        encoder.finish().unwrap();
+
+        let packages: Vec<Package> = from_file(&path).unwrap().collect::<Result<_, _>>().unwrap();
+        assert_eq!(packages.len(), 3);
+    }
+}
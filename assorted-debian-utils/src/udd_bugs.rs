@@ -0,0 +1,841 @@
+// Copyright 2022 Sebastian Ramacher
+// SPDX-License-Identifier: LGPL-3.0-or-later
+
+//! # Helpers to handle bug lists from UDD
+//!
+//! This module provides [UDDBugsQuery] to build queries for the YAML interface of UDD's bugs
+//! search and [UDDBugs] to represent the returned bug lists. The module is only available with the
+//! `fetch` feature. The bug lists can be fetched with [Fetcher::udd_bugs](crate::fetch::Fetcher::udd_bugs).
+//!
+//! ```
+//! use assorted_debian_utils::udd_bugs::{Filter, UDDBugsQuery};
+//!
+//! let mut query = UDDBugsQuery::new();
+//! query
+//!     .with_release("sid")
+//!     .with_rc()
+//!     .with_tag("ftbfs", Filter::Only)
+//!     .expect("ftbfs is a valid tag");
+//! assert_eq!(
+//!     query.to_string(),
+//!     "release=sid&rc=1&ftbfs=only&merged=ign&done=ign&sortby=id&sorto=asc"
+//! );
+//! ```
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::{Display, Formatter},
+    io::Read,
+    str::FromStr,
+};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{de, Deserialize, Deserializer};
+use sha2::{Digest, Sha256};
+
+use crate::archive::Suite;
+
+/// URL of UDD's bugs search
+pub const UDD_BUGS_URL: &str = "https://udd.debian.org/bugs/";
+
+/// Error when building UDD bugs queries
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// Given string is not a valid filter
+    InvalidFilter(String),
+    /// Given string is not a valid sort key
+    InvalidSortKey(String),
+    /// Given string is not a valid tag
+    InvalidTag(String),
+    /// Given string is not a valid query parameter
+    InvalidParameter(String),
+    /// A usertag was given without user or tag
+    IncompleteUsertag,
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::InvalidFilter(filter) => write!(f, "invalid filter: {}", filter),
+            Error::InvalidSortKey(sort_by) => write!(f, "invalid sort key: {}", sort_by),
+            Error::InvalidTag(tag) => write!(f, "invalid tag: {}", tag),
+            Error::InvalidParameter(parameter) => {
+                write!(f, "invalid query parameter: {}", parameter)
+            }
+            Error::IncompleteUsertag => write!(f, "usertag requires user and tag"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Result type
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Severity of a bug
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Severity `wishlist`
+    Wishlist,
+    /// Severity `minor`
+    Minor,
+    /// Severity `normal`
+    Normal,
+    /// Severity `important`
+    Important,
+    /// Severity `serious`
+    Serious,
+    /// Severity `grave`
+    Grave,
+    /// Severity `critical`
+    Critical,
+}
+
+impl Severity {
+    /// Check if the severity is release-critical
+    pub fn is_rc(&self) -> bool {
+        self >= &Severity::Serious
+    }
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Wishlist => write!(f, "wishlist"),
+            Severity::Minor => write!(f, "minor"),
+            Severity::Normal => write!(f, "normal"),
+            Severity::Important => write!(f, "important"),
+            Severity::Serious => write!(f, "serious"),
+            Severity::Grave => write!(f, "grave"),
+            Severity::Critical => write!(f, "critical"),
+        }
+    }
+}
+
+/// Status of a bug
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Status {
+    /// The bug is open
+    Pending,
+    /// The bug is tagged `pending`
+    PendingFixed,
+    /// The bug is forwarded upstream
+    Forwarded,
+    /// The bug is tagged `fixed`
+    Fixed,
+    /// The bug is closed
+    Done,
+}
+
+impl Display for Status {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Status::Pending => write!(f, "pending"),
+            Status::PendingFixed => write!(f, "pending-fixed"),
+            Status::Forwarded => write!(f, "forwarded"),
+            Status::Fixed => write!(f, "fixed"),
+            Status::Done => write!(f, "done"),
+        }
+    }
+}
+
+// UDD uses empty strings for unset fields
+fn deserialize_optional_string<'de, D>(
+    deserializer: D,
+) -> std::result::Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Option::<String>::deserialize(deserializer)?;
+    Ok(value.filter(|value| !value.is_empty()))
+}
+
+fn deserialize_optional_date<'de, D>(
+    deserializer: D,
+) -> std::result::Result<Option<NaiveDate>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match deserialize_optional_string(deserializer)? {
+        Some(value) => NaiveDate::parse_from_str(&value, "%Y-%m-%d")
+            .map(Some)
+            .map_err(de::Error::custom),
+        None => Ok(None),
+    }
+}
+
+fn deserialize_datetime<'de, D>(deserializer: D) -> std::result::Result<DateTime<Utc>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+    DateTime::parse_from_str(&value, "%Y-%m-%d %H:%M:%S %z")
+        .map(|datetime| datetime.with_timezone(&Utc))
+        .map_err(de::Error::custom)
+}
+
+// tags are given as a space or comma separated list
+fn deserialize_tags<'de, D>(deserializer: D) -> std::result::Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Option::<String>::deserialize(deserializer)?;
+    Ok(value.map_or_else(Vec::new, |value| {
+        value
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|tag| !tag.is_empty())
+            .map(String::from)
+            .collect()
+    }))
+}
+
+/// A bug as returned by UDD
+#[derive(Clone, Debug, Deserialize)]
+pub struct UDDBug {
+    /// The bug number
+    pub id: u32,
+    /// Package the bug is filed against, e.g., `src:foo` or `foo`
+    pub package: String,
+    /// Source package of the package the bug is filed against
+    pub source: String,
+    /// Severity of the bug
+    pub severity: Severity,
+    /// Title of the bug
+    pub title: String,
+    /// Status of the bug
+    pub status: Status,
+    /// Date of the last modification
+    #[serde(deserialize_with = "deserialize_datetime")]
+    pub last_modified_full: DateTime<Utc>,
+    /// The bug affects stable
+    pub affects_stable: bool,
+    /// The bug affects testing
+    pub affects_testing: bool,
+    /// The bug affects unstable
+    pub affects_unstable: bool,
+    /// The bug affects experimental
+    pub affects_experimental: bool,
+    /// Date of the scheduled auto-removal from testing
+    #[serde(default, deserialize_with = "deserialize_optional_date")]
+    pub autormdate: Option<NaiveDate>,
+    /// Tags of the bug
+    #[serde(default, deserialize_with = "deserialize_tags")]
+    pub tags: Vec<String>,
+    /// Where the bug was forwarded to
+    #[serde(default, deserialize_with = "deserialize_optional_string")]
+    pub forwarded: Option<String>,
+}
+
+impl Display for UDDBug {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "#{} {}: {} ({}, {}",
+            self.id, self.package, self.title, self.severity, self.status
+        )?;
+        if !self.tags.is_empty() {
+            write!(f, ", tags: {}", self.tags.join(" "))?;
+        }
+        if let Some(forwarded) = &self.forwarded {
+            write!(f, ", forwarded: {}", forwarded)?;
+        }
+        if let Some(autormdate) = &self.autormdate {
+            write!(f, ", auto-removal: {}", autormdate)?;
+        }
+        write!(
+            f,
+            ", last modified: {})",
+            self.last_modified_full.date_naive()
+        )
+    }
+}
+
+impl UDDBug {
+    /// Check if the bug is release-critical
+    pub fn is_rc(&self) -> bool {
+        self.severity.is_rc()
+    }
+
+    /// Check if the bug affects a suite
+    ///
+    /// UDD does not track oldstable, so `None` is returned for it.
+    pub fn affects(&self, suite: Suite) -> Option<bool> {
+        match suite {
+            Suite::Unstable => Some(self.affects_unstable),
+            Suite::Testing(_) => Some(self.affects_testing),
+            Suite::Stable(_) => Some(self.affects_stable),
+            Suite::OldStable(_) => None,
+            Suite::Experimental => Some(self.affects_experimental),
+        }
+    }
+}
+
+/// A list of bugs indexed by source package
+#[derive(Debug, Default)]
+pub struct UDDBugs {
+    bugs: Vec<UDDBug>,
+    source_index: HashMap<String, Vec<usize>>,
+}
+
+impl UDDBugs {
+    /// Create a new list from bugs
+    pub fn new(bugs: Vec<UDDBug>) -> Self {
+        let mut udd_bugs = Self {
+            bugs,
+            ..Default::default()
+        };
+
+        for (idx, bug) in udd_bugs.bugs.iter().enumerate() {
+            if udd_bugs.source_index.contains_key(&bug.source) {
+                udd_bugs
+                    .source_index
+                    .get_mut(&bug.source)
+                    .unwrap()
+                    .push(idx);
+            } else {
+                udd_bugs.source_index.insert(bug.source.clone(), vec![idx]);
+            }
+        }
+
+        udd_bugs
+    }
+
+    /// Iterate over all bugs
+    pub fn iter(&self) -> impl Iterator<Item = &UDDBug> {
+        self.bugs.iter()
+    }
+
+    /// Return the bugs of a source package
+    ///
+    /// If a suite is given, bugs known not to affect that suite are skipped. Bugs are kept if it is
+    /// unknown whether they affect the suite.
+    pub fn bugs_for_source(&self, source: &str, suite: Option<Suite>) -> Option<Vec<&UDDBug>> {
+        let bugs: Vec<_> = self
+            .source_index
+            .get(source)?
+            .iter()
+            .map(|idx| &self.bugs[*idx])
+            .filter(|bug| suite.map_or(true, |suite| bug.affects(suite) != Some(false)))
+            .collect();
+        if bugs.is_empty() {
+            None
+        } else {
+            Some(bugs)
+        }
+    }
+}
+
+/// Filter for a property of bugs in UDD queries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Filter {
+    /// Include bugs regardless of the property
+    Include,
+    /// Only include bugs with the property
+    Only,
+    /// Ignore bugs with the property
+    Ignore,
+}
+
+impl FromStr for Filter {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "" | "include" => Ok(Filter::Include),
+            "only" => Ok(Filter::Only),
+            "ign" | "ignore" => Ok(Filter::Ignore),
+            _ => Err(Error::InvalidFilter(s.to_owned())),
+        }
+    }
+}
+
+/// Sort key of UDD queries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SortBy {
+    /// Sort by bug number
+    Id,
+    /// Sort by source package
+    Source,
+    /// Sort by package
+    Package,
+    /// Sort by date of the last modification
+    LastModified,
+    /// Sort by severity
+    Severity,
+}
+
+impl Display for SortBy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SortBy::Id => write!(f, "id"),
+            SortBy::Source => write!(f, "source"),
+            SortBy::Package => write!(f, "package"),
+            SortBy::LastModified => write!(f, "last_modified"),
+            SortBy::Severity => write!(f, "severity"),
+        }
+    }
+}
+
+impl FromStr for SortBy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "id" => Ok(SortBy::Id),
+            "source" => Ok(SortBy::Source),
+            "package" => Ok(SortBy::Package),
+            "last_modified" | "last-modified" => Ok(SortBy::LastModified),
+            "severity" => Ok(SortBy::Severity),
+            _ => Err(Error::InvalidSortKey(s.to_owned())),
+        }
+    }
+}
+
+/// Query for the YAML interface of UDD's bugs search
+///
+/// The query is built with the `with_*` methods. Its string representation lists the URL-encoded
+/// parameters in a fixed order and is used to derive the key under which the results are cached.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UDDBugsQuery {
+    release: Option<String>,
+    rc: bool,
+    tags: BTreeMap<String, Filter>,
+    usertag: Option<(String, String)>,
+    merged: Filter,
+    done: Filter,
+    sort_by: SortBy,
+    descending: bool,
+}
+
+impl Default for UDDBugsQuery {
+    fn default() -> Self {
+        Self {
+            release: None,
+            rc: false,
+            tags: BTreeMap::new(),
+            usertag: None,
+            merged: Filter::Ignore,
+            done: Filter::Ignore,
+            sort_by: SortBy::Id,
+            descending: false,
+        }
+    }
+}
+
+impl UDDBugsQuery {
+    /// Create a new query for all bugs that are neither merged nor done
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only include bugs affecting the release with the given codename
+    pub fn with_release(&mut self, codename: &str) -> &mut Self {
+        self.release = Some(codename.to_owned());
+        self
+    }
+
+    /// Only include release-critical bugs
+    pub fn with_rc(&mut self) -> &mut Self {
+        self.rc = true;
+        self
+    }
+
+    /// Filter bugs by a tag or category known to UDD, e.g., `ftbfs`, `moreinfo` or `security`
+    pub fn with_tag(&mut self, tag: &str, filter: Filter) -> Result<&mut Self> {
+        if tag.is_empty()
+            || !tag
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        {
+            return Err(Error::InvalidTag(tag.to_owned()));
+        }
+        if filter == Filter::Include {
+            self.tags.remove(tag);
+        } else {
+            self.tags.insert(tag.to_owned(), filter);
+        }
+        Ok(self)
+    }
+
+    /// Filter bugs by the `patch` tag
+    pub fn with_patch(&mut self, filter: Filter) -> &mut Self {
+        self.with_tag("patch", filter)
+            .expect("patch is a valid tag")
+    }
+
+    /// Filter bugs by the `pending` tag
+    pub fn with_pending(&mut self, filter: Filter) -> &mut Self {
+        self.with_tag("pending", filter)
+            .expect("pending is a valid tag")
+    }
+
+    /// Only include bugs with the given usertag
+    pub fn with_usertag(&mut self, user: &str, tag: &str) -> &mut Self {
+        self.usertag = Some((user.to_owned(), tag.to_owned()));
+        self
+    }
+
+    /// Filter merged bugs. If not set, merged bugs are ignored.
+    pub fn with_merged(&mut self, filter: Filter) -> &mut Self {
+        self.merged = filter;
+        self
+    }
+
+    /// Filter done bugs. If not set, done bugs are ignored.
+    pub fn with_done(&mut self, filter: Filter) -> &mut Self {
+        self.done = filter;
+        self
+    }
+
+    /// Sort the bugs. If not set, the bugs are sorted by ascending ID.
+    pub fn with_sort(&mut self, sort_by: SortBy, descending: bool) -> &mut Self {
+        self.sort_by = sort_by;
+        self.descending = descending;
+        self
+    }
+
+    fn parameters(&self) -> Vec<(String, String)> {
+        let mut parameters = vec![];
+        match &self.release {
+            Some(release) => parameters.push(("release".into(), release.clone())),
+            None => {
+                parameters.push(("release".into(), "na".into()));
+                parameters.push(("allbugs".into(), "1".into()));
+            }
+        }
+        if self.rc {
+            parameters.push(("rc".into(), "1".into()));
+        }
+        let filter_value = |filter: Filter| match filter {
+            Filter::Include => "".to_owned(),
+            Filter::Only => "only".to_owned(),
+            Filter::Ignore => "ign".to_owned(),
+        };
+        // with_tag only keeps tags that are not included
+        for (tag, filter) in &self.tags {
+            parameters.push((tag.clone(), filter_value(*filter)));
+        }
+        if let Some((user, tag)) = &self.usertag {
+            parameters.push(("fusertag".into(), "only".into()));
+            parameters.push(("fusertaguser".into(), user.clone()));
+            parameters.push(("fusertagtag".into(), tag.clone()));
+        }
+        // UDD includes merged and done bugs unless told otherwise, so they are always given
+        parameters.push(("merged".into(), filter_value(self.merged)));
+        parameters.push(("done".into(), filter_value(self.done)));
+        parameters.push(("sortby".into(), self.sort_by.to_string()));
+        parameters.push((
+            "sorto".into(),
+            if self.descending { "desc" } else { "asc" }.into(),
+        ));
+        parameters
+    }
+
+    /// URL of the query returning YAML
+    pub fn url(&self) -> String {
+        format!("{}?{}&format=yaml", UDD_BUGS_URL, self)
+    }
+
+    /// Stable key of the query for caching
+    pub fn key(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.to_string());
+        format!("{:x}", hasher.finalize())[..16].to_owned()
+    }
+}
+
+impl Display for UDDBugsQuery {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut serializer = form_urlencoded::Serializer::new(String::new());
+        serializer.extend_pairs(self.parameters());
+        write!(f, "{}", serializer.finish())
+    }
+}
+
+impl FromStr for UDDBugsQuery {
+    type Err = Error;
+
+    /// Parse the URL-encoded parameters of a query, e.g., `release=sid&rc=1&ftbfs=only`
+    ///
+    /// As with [UDDBugsQuery::new], merged and done bugs are ignored unless given otherwise.
+    fn from_str(s: &str) -> Result<Self> {
+        if let Some(parameter) = s
+            .split('&')
+            .find(|parameter| !parameter.is_empty() && !parameter.contains('='))
+        {
+            return Err(Error::InvalidParameter(parameter.to_owned()));
+        }
+
+        let mut query = Self::new();
+        let mut user = None;
+        let mut tag = None;
+        for (key, value) in form_urlencoded::parse(s.as_bytes()) {
+            match key.as_ref() {
+                "release" => {
+                    query.release = (value != "na").then(|| value.to_string());
+                }
+                "rc" => query.rc = value == "1",
+                "allbugs" | "fusertag" | "format" => {}
+                "fusertaguser" => user = Some(value),
+                "fusertagtag" => tag = Some(value),
+                "merged" => query.merged = value.parse()?,
+                "done" => query.done = value.parse()?,
+                "sortby" => query.sort_by = value.parse()?,
+                "sorto" => query.descending = value == "desc",
+                _ => {
+                    query.with_tag(&key, value.parse()?)?;
+                }
+            }
+        }
+        match (user, tag) {
+            (Some(user), Some(tag)) => {
+                query.with_usertag(&user, &tag);
+            }
+            (None, None) => {}
+            _ => return Err(Error::IncompleteUsertag),
+        }
+        Ok(query)
+    }
+}
+
+/// Read bugs from a reader
+pub fn from_reader(reader: impl Read) -> serde_yaml::Result<UDDBugs> {
+    serde_yaml::from_reader(reader).map(UDDBugs::new)
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{NaiveDate, TimeZone, Utc};
+
+    use super::{from_reader, Filter, Severity, SortBy, Status, UDDBugsQuery};
+    use crate::archive::Suite;
+
+    const TEST_DATA: &str = r#"
+---
+- id: 743062
+  package: src:mutextrace
+  source: mutextrace
+  severity: serious
+  title: 'mutextrace: sometimes FTBFS: testsuite races'
+  last_modified: '2021-08-16'
+  status: pending
+  affects_stable: false
+  affects_testing: false
+  affects_unstable: true
+  affects_experimental: false
+  last_modified_full: '2021-08-16 07:03:39 +0000'
+  autormdate: ''
+- id: 778111
+  package: src:scheme2c
+  source: scheme2c
+  severity: serious
+  title: 'scheme2c: ftbfs with GCC-5'
+  last_modified: '2021-08-16'
+  status: pending
+  affects_stable: false
+  affects_testing: false
+  affects_unstable: true
+  affects_experimental: false
+  last_modified_full: '2021-08-16 07:03:46 +0000'
+  autormdate: ''
+- id: 789292
+  package: src:dmtcp
+  source: dmtcp
+  severity: serious
+  title: 'dmtcp: FTBFS with glibc-2.21 and gcc-5'
+  last_modified: '2021-11-16'
+  status: forwarded
+  affects_stable: false
+  affects_testing: false
+  affects_unstable: true
+  affects_experimental: false
+  last_modified_full: '2021-11-16 23:03:16 +0000'
+  autormdate: ''
+  tags: 'upstream patch'
+  forwarded: 'https://github.com/dmtcp/dmtcp/issues/1'
+- id: 1000001
+  package: libfoo1
+  source: foo
+  severity: important
+  title: 'libfoo1: crashes'
+  last_modified: '2022-04-01'
+  status: pending
+  affects_stable: false
+  affects_testing: true
+  affects_unstable: true
+  affects_experimental: false
+  last_modified_full: '2022-04-01 12:00:00 +0200'
+  autormdate: '2022-05-01'
+- id: 1000002
+  package: foo-doc
+  source: foo
+  severity: minor
+  title: 'foo-doc: typo in manual page'
+  last_modified: '2022-04-02'
+  status: pending
+  affects_stable: false
+  affects_testing: true
+  affects_unstable: true
+  affects_experimental: false
+  last_modified_full: '2022-04-02 08:00:00 +0000'
+  autormdate: ''
+"#;
+
+    #[test]
+    fn read_bugs() {
+        let bugs = from_reader(TEST_DATA.as_bytes()).unwrap();
+
+        assert!(bugs.bugs_for_source("dmtcp", None).is_some());
+        assert!(bugs.bugs_for_source("zathura", None).is_none());
+
+        for bug in bugs.bugs_for_source("mutextrace", None).unwrap() {
+            assert!(bug.severity >= Severity::Serious);
+            assert!(bug.severity.is_rc());
+        }
+    }
+
+    #[test]
+    fn fields() {
+        let bugs = from_reader(TEST_DATA.as_bytes()).unwrap();
+
+        let bug = bugs.bugs_for_source("dmtcp", None).unwrap()[0];
+        assert_eq!(bug.package, "src:dmtcp");
+        assert_eq!(bug.status, Status::Forwarded);
+        assert_eq!(
+            bug.last_modified_full,
+            Utc.with_ymd_and_hms(2021, 11, 16, 23, 3, 16).unwrap()
+        );
+        assert_eq!(bug.autormdate, None);
+        assert_eq!(bug.tags, vec!["upstream", "patch"]);
+        assert_eq!(
+            bug.forwarded.as_deref(),
+            Some("https://github.com/dmtcp/dmtcp/issues/1")
+        );
+
+        let bug = bugs.bugs_for_source("foo", None).unwrap()[0];
+        assert!(!bug.is_rc());
+        assert_eq!(
+            bug.last_modified_full,
+            Utc.with_ymd_and_hms(2022, 4, 1, 10, 0, 0).unwrap()
+        );
+        assert_eq!(bug.autormdate, NaiveDate::from_ymd_opt(2022, 5, 1));
+        assert!(bug.tags.is_empty());
+        assert_eq!(bug.forwarded, None);
+    }
+
+    #[test]
+    fn affected_suites() {
+        let bugs = from_reader(TEST_DATA.as_bytes()).unwrap();
+
+        assert!(bugs
+            .bugs_for_source("dmtcp", Some(Suite::Unstable))
+            .is_some());
+        assert!(bugs
+            .bugs_for_source("dmtcp", Some(Suite::Testing(None)))
+            .is_none());
+        assert!(bugs
+            .bugs_for_source("foo", Some(Suite::Testing(None)))
+            .is_some());
+        assert!(bugs
+            .bugs_for_source("foo", Some(Suite::Stable(None)))
+            .is_none());
+        assert!(bugs
+            .bugs_for_source("zathura", Some(Suite::Unstable))
+            .is_none());
+        // oldstable is not tracked by UDD
+        assert!(bugs
+            .bugs_for_source("dmtcp", Some(Suite::OldStable(None)))
+            .is_some());
+
+        let bug = bugs.bugs_for_source("dmtcp", None).unwrap()[0];
+        assert_eq!(bug.affects(Suite::Unstable), Some(true));
+        assert_eq!(bug.affects(Suite::Stable(None)), Some(false));
+        assert_eq!(bug.affects(Suite::OldStable(None)), None);
+    }
+
+    #[test]
+    fn query() {
+        let mut query = UDDBugsQuery::new();
+        query
+            .with_release("trixie")
+            .with_rc()
+            .with_tag("ftbfs", Filter::Only)
+            .unwrap();
+        assert_eq!(
+            query.url(),
+            "https://udd.debian.org/bugs/?release=trixie&rc=1&ftbfs=only&merged=ign&done=ign&sortby=id&sorto=asc&format=yaml"
+        );
+
+        let mut other = UDDBugsQuery::new();
+        other
+            .with_tag("ftbfs", Filter::Only)
+            .unwrap()
+            .with_rc()
+            .with_release("trixie");
+        assert_eq!(query, other);
+        assert_eq!(query.key(), other.key());
+        other.with_patch(Filter::Ignore);
+        assert_ne!(query.key(), other.key());
+
+        let mut query = UDDBugsQuery::new();
+        query
+            .with_usertag("helmutg@debian.org", "dep17p1")
+            .with_pending(Filter::Only)
+            .with_merged(Filter::Include)
+            .with_done(Filter::Only)
+            .with_sort(SortBy::LastModified, true);
+        assert_eq!(
+            query.to_string(),
+            "release=na&allbugs=1&pending=only&fusertag=only&fusertaguser=helmutg%40debian.org&fusertagtag=dep17p1&merged=&done=only&sortby=last_modified&sorto=desc"
+        );
+        assert_eq!(query.to_string().parse::<UDDBugsQuery>().unwrap(), query);
+
+        // user and tag are encoded
+        let mut query = UDDBugsQuery::new();
+        query.with_usertag("foo+bar@example.org", "a&b=c d");
+        assert!(query
+            .url()
+            .contains("fusertaguser=foo%2Bbar%40example.org&fusertagtag=a%26b%3Dc+d&"));
+        assert_eq!(query.to_string().parse::<UDDBugsQuery>().unwrap(), query);
+
+        // omitted parameters match the defaults of a new query
+        let mut query = UDDBugsQuery::new();
+        query.with_release("sid");
+        assert_eq!("release=sid".parse::<UDDBugsQuery>().unwrap(), query);
+
+        assert!(UDDBugsQuery::new().with_tag("FTBFS", Filter::Only).is_err());
+        assert!("merged=foo".parse::<UDDBugsQuery>().is_err());
+        assert!("fusertaguser=foo".parse::<UDDBugsQuery>().is_err());
+    }
+
+    #[test]
+    fn display() {
+        let bugs = from_reader(TEST_DATA.as_bytes()).unwrap();
+        assert_eq!(bugs.iter().count(), 5);
+        assert_eq!(
+            bugs.bugs_for_source("dmtcp", None).unwrap()[0].to_string(),
+            "#789292 src:dmtcp: dmtcp: FTBFS with glibc-2.21 and gcc-5 (serious, forwarded, tags: upstream patch, forwarded: https://github.com/dmtcp/dmtcp/issues/1, last modified: 2021-11-16)"
+        );
+        assert_eq!(
+            bugs.bugs_for_source("foo", None).unwrap()[0].to_string(),
+            "#1000001 libfoo1: libfoo1: crashes (important, pending, auto-removal: 2022-05-01, last modified: 2022-04-01)"
+        );
+        assert_eq!(
+            bugs.bugs_for_source("foo", None).unwrap()[1].to_string(),
+            "#1000002 foo-doc: foo-doc: typo in manual page (minor, pending, last modified: 2022-04-02)"
+        );
+    }
+
+    #[test]
+    fn severity() {
+        let bugs = from_reader(TEST_DATA.as_bytes()).unwrap();
+
+        let bug = bugs.bugs_for_source("foo", None).unwrap()[1];
+        assert_eq!(bug.severity, Severity::Minor);
+        assert!(!bug.is_rc());
+        assert!(Severity::Wishlist < Severity::Minor);
+        assert!(Severity::Minor < Severity::Normal);
+    }
+}
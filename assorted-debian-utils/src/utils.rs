@@ -5,9 +5,11 @@
 
 use std::fmt;
 
-use chrono::{DateTime, TimeZone, Utc};
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
 use serde::de;
 
+use crate::archive::Checksum;
+
 #[derive(Debug)]
 pub(crate) struct DateTimeVisitor<'a>(pub &'a str);
 
@@ -22,9 +24,117 @@ impl<'de> de::Visitor<'de> for DateTimeVisitor<'_> {
     where
         E: de::Error,
     {
-        match Utc.datetime_from_str(s, self.0) {
-            Ok(dt) => Ok(dt),
+        match NaiveDateTime::parse_from_str(s, self.0) {
+            Ok(dt) => Ok(Utc.from_utc_datetime(&dt)),
+            Err(_) => Err(de::Error::invalid_value(de::Unexpected::Str(s), &self)),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct RFC2822DateTimeVisitor;
+
+impl<'de> de::Visitor<'de> for RFC2822DateTimeVisitor {
+    type Value = DateTime<Utc>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a date and time formatted according to RFC 2822")
+    }
+
+    fn visit_str<E>(self, s: &str) -> std::result::Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        // Release files use UTC instead of a numerical offset
+        let parsed = match s.strip_suffix(" UTC") {
+            Some(stripped) => DateTime::parse_from_rfc2822(&format!("{} +0000", stripped)),
+            None => DateTime::parse_from_rfc2822(s),
+        };
+        match parsed {
+            Ok(dt) => Ok(dt.with_timezone(&Utc)),
             Err(_) => Err(de::Error::invalid_value(de::Unexpected::Str(s), &self)),
         }
     }
 }
+
+/// Deserialize a RFC 2822 datetime string into a `DateTime<Utc>`
+pub(crate) fn deserialize_rfc2822_datetime<'de, D>(
+    deserializer: D,
+) -> std::result::Result<DateTime<Utc>, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    deserializer.deserialize_str(RFC2822DateTimeVisitor)
+}
+
+/// Deserialize an optional RFC 2822 datetime string into a `DateTime<Utc>`
+pub(crate) fn deserialize_optional_rfc2822_datetime<'de, D>(
+    deserializer: D,
+) -> std::result::Result<Option<DateTime<Utc>>, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    deserialize_rfc2822_datetime(deserializer).map(Some)
+}
+
+#[derive(Debug)]
+struct WhitespaceSeparatedVisitor;
+
+impl<'de> de::Visitor<'de> for WhitespaceSeparatedVisitor {
+    type Value = Vec<String>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a whitespace separated list")
+    }
+
+    fn visit_str<E>(self, s: &str) -> std::result::Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(s.split_whitespace().map(String::from).collect())
+    }
+}
+
+/// Deserialize a whitespace separated list (possibly spanning multiple lines) of strings
+pub(crate) fn deserialize_whitespace_separated<'de, D>(
+    deserializer: D,
+) -> std::result::Result<Vec<String>, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    deserializer.deserialize_str(WhitespaceSeparatedVisitor)
+}
+
+#[derive(Debug)]
+struct ChecksumsVisitor;
+
+impl<'de> de::Visitor<'de> for ChecksumsVisitor {
+    type Value = Vec<Checksum>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a list of checksums, sizes and file names")
+    }
+
+    fn visit_str<E>(self, s: &str) -> std::result::Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        s.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                Checksum::try_from(line)
+                    .map_err(|_| de::Error::invalid_value(de::Unexpected::Str(line), &self))
+            })
+            .collect()
+    }
+}
+
+/// Deserialize a multi-line list of checksums
+pub(crate) fn deserialize_checksums<'de, D>(
+    deserializer: D,
+) -> std::result::Result<Vec<Checksum>, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    deserializer.deserialize_str(ChecksumsVisitor)
+}
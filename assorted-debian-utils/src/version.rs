@@ -18,14 +18,12 @@
 //! assert!(ver2.has_epoch());
 //! assert!(!ver2.is_native());
 //!
-//! #[cfg(feature="libdpkg-sys")]
-//! {
-//!     assert!(ver1 < ver2);
-//!     assert_eq!(ver1, PackageVersion::new(Some(0), "1.0", Some("2")).expect("Failed to construct version"));
-//! }
+//! assert!(ver1 < ver2);
+//! assert_eq!(ver1, PackageVersion::new(Some(0), "1.0", Some("2")).expect("Failed to construct version"));
 //! ```
 
 use std::{
+    cmp::Ordering,
     error::Error,
     fmt::{self, Display},
     hash::{Hash, Hasher},
@@ -159,9 +157,6 @@ impl PackageVersion {
     }
 }
 
-#[cfg(feature = "libdpkg-sys")]
-use std::cmp::Ordering;
-
 #[cfg(feature = "libdpkg-sys")]
 use crate::cversion::CVersion;
 
@@ -195,6 +190,90 @@ impl PartialEq for PackageVersion {
     }
 }
 
+/// Weight of a character in a non-digit part as defined by dpkg
+#[cfg(not(feature = "libdpkg-sys"))]
+fn order(c: Option<&u8>) -> i32 {
+    match c {
+        None => 0,
+        Some(c) if c.is_ascii_digit() => 0,
+        Some(c) if c.is_ascii_alphabetic() => *c as i32,
+        Some(b'~') => -1,
+        Some(c) => *c as i32 + 256,
+    }
+}
+
+/// Compare upstream versions or Debian revisions following the algorithm of dpkg
+#[cfg(not(feature = "libdpkg-sys"))]
+fn compare_part(lhs: &str, rhs: &str) -> Ordering {
+    let (lhs, rhs) = (lhs.as_bytes(), rhs.as_bytes());
+    let (mut i, mut j) = (0, 0);
+    let is_digit = |s: &[u8], idx: usize| s.get(idx).is_some_and(u8::is_ascii_digit);
+
+    while i < lhs.len() || j < rhs.len() {
+        // compare non-digit prefix
+        while (i < lhs.len() && !is_digit(lhs, i)) || (j < rhs.len() && !is_digit(rhs, j)) {
+            let (lc, rc) = (order(lhs.get(i)), order(rhs.get(j)));
+            if lc != rc {
+                return lc.cmp(&rc);
+            }
+            i += 1;
+            j += 1;
+        }
+
+        // compare numerical part
+        while lhs.get(i) == Some(&b'0') {
+            i += 1;
+        }
+        while rhs.get(j) == Some(&b'0') {
+            j += 1;
+        }
+        let mut first_diff = Ordering::Equal;
+        while is_digit(lhs, i) && is_digit(rhs, j) {
+            if first_diff == Ordering::Equal {
+                first_diff = lhs[i].cmp(&rhs[j]);
+            }
+            i += 1;
+            j += 1;
+        }
+        if is_digit(lhs, i) {
+            return Ordering::Greater;
+        }
+        if is_digit(rhs, j) {
+            return Ordering::Less;
+        }
+        if first_diff != Ordering::Equal {
+            return first_diff;
+        }
+    }
+    Ordering::Equal
+}
+
+#[cfg(not(feature = "libdpkg-sys"))]
+impl PartialOrd for PackageVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(not(feature = "libdpkg-sys"))]
+impl Ord for PackageVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.epoch_or_0()
+            .cmp(&other.epoch_or_0())
+            .then_with(|| compare_part(&self.upstream_version, &other.upstream_version))
+            .then_with(|| {
+                compare_part(
+                    self.debian_revision.as_deref().unwrap_or(""),
+                    other.debian_revision.as_deref().unwrap_or(""),
+                )
+            })
+            // versions like 1.0 and 1.00 are considered equal by dpkg; tie-break on the string
+            // representation to stay consistent with PartialEq
+            .then_with(|| self.upstream_version.cmp(&other.upstream_version))
+            .then_with(|| self.debian_revision.cmp(&other.debian_revision))
+    }
+}
+
 impl Eq for PackageVersion {}
 
 impl TryFrom<&str> for PackageVersion {
@@ -295,7 +374,6 @@ mod test {
         assert_eq!(version.debian_revision, Some("1".into()));
     }
 
-    #[cfg(feature = "libdpkg-sys")]
     #[test]
     fn epoch_compare() {
         let version1 = PackageVersion::try_from("2.0-1").unwrap();
@@ -306,6 +384,28 @@ mod test {
         assert!(version1 < version2);
     }
 
+    #[test]
+    fn compare() {
+        for (lhs, rhs) in [
+            ("1.0-1", "1.0-2"),
+            ("1.0~rc1-1", "1.0-1"),
+            ("1.0-1", "1.0-1+b1"),
+            ("1.0-1~bpo11+1", "1.0-1"),
+            ("1.0", "1.0-1"),
+            ("1.9", "1.10"),
+            ("1.0a", "1.0b"),
+            ("1.0", "1.0a"),
+            ("1.0a", "1.0+dfsg"),
+            ("12.2.0-3", "12.2.0-14"),
+            ("2.0-1", "1:1.0-1"),
+        ] {
+            let lhs = PackageVersion::try_from(lhs).unwrap();
+            let rhs = PackageVersion::try_from(rhs).unwrap();
+            assert!(lhs < rhs, "{} < {}", lhs, rhs);
+            assert!(rhs > lhs, "{} > {}", rhs, lhs);
+        }
+    }
+
     #[test]
     fn zero_epoch_compare() {
         let version1 = PackageVersion::try_from("2.0-1").unwrap();
@@ -85,7 +85,7 @@ pub trait WBCommandBuilder {
 /// named `ANY` (all binary-dependent architectures) and `ALL` (all architectures). Also, it
 /// supports negation of architectures, e.g., `ANY -i386` refers to all binary-dependent
 /// architectures without `i386`.
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub enum WBArchitecture {
     /// The special `ANY` architecture, i.e., all architectures understood by wb except `all`
     Any,
@@ -169,7 +169,7 @@ impl<'a> SourceSpecifier<'a> {
         self.architectures.extend(
             architectures
                 .iter()
-                .copied()
+                .cloned()
                 .map(WBArchitecture::Architecture),
         );
         self
@@ -190,15 +190,10 @@ impl<'a> Display for SourceSpecifier<'a> {
                 write!(f, "{} ", arch)?;
             }
         }
-        write!(
-            f,
-            ". {}",
-            if let Some(suite) = self.suite {
-                *suite
-            } else {
-                SuiteOrCodename::Suite(Suite::Unstable)
-            },
-        )
+        match self.suite {
+            Some(suite) => write!(f, ". {}", suite),
+            None => write!(f, ". {}", Suite::Unstable),
+        }
     }
 }
 
@@ -224,7 +219,7 @@ impl<'a> BinNMU<'a> {
                 | WBArchitecture::Architecture(Architecture::All)
                 | WBArchitecture::ExcludeArchitecture(Architecture::All)
                 | WBArchitecture::All => {
-                    return Err(Error::InvalidArchitecture(*arch, "nmu"));
+                    return Err(Error::InvalidArchitecture(arch.clone(), "nmu"));
                 }
                 _ => {}
             }
@@ -319,7 +314,7 @@ impl<'a> DepWait<'a> {
                 // unable to dw with source, -source
                 WBArchitecture::Architecture(Architecture::Source)
                 | WBArchitecture::ExcludeArchitecture(Architecture::Source) => {
-                    return Err(Error::InvalidArchitecture(*arch, "dw"));
+                    return Err(Error::InvalidArchitecture(arch.clone(), "dw"));
                 }
                 _ => {}
             }
@@ -356,7 +351,7 @@ impl<'a> BuildPriority<'a> {
                 // unable to bp with source, -source
                 WBArchitecture::Architecture(Architecture::Source)
                 | WBArchitecture::ExcludeArchitecture(Architecture::Source) => {
-                    return Err(Error::InvalidArchitecture(*arch, "bp"));
+                    return Err(Error::InvalidArchitecture(arch.clone(), "bp"));
                 }
                 _ => {}
             }
@@ -393,7 +388,7 @@ impl<'a> Fail<'a> {
                 // unable to fail with source, -source
                 WBArchitecture::Architecture(Architecture::Source)
                 | WBArchitecture::ExcludeArchitecture(Architecture::Source) => {
-                    return Err(Error::InvalidArchitecture(*arch, "fail"));
+                    return Err(Error::InvalidArchitecture(arch.clone(), "fail"));
                 }
                 _ => {}
             }
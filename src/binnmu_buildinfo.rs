@@ -1,20 +1,23 @@
 // Copyright 2022 Sebastian Ramacher
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use std::io::{BufRead, BufReader, Write};
-use std::path::PathBuf;
-use std::{collections::HashSet, fs::File};
+use std::path::{Path, PathBuf};
+use std::{collections::HashMap, fs};
 
 use anyhow::{anyhow, Result};
 use assorted_debian_utils::{
-    architectures::{Architecture, RELEASE_ARCHITECTURES},
+    architectures::Architecture,
+    archive::Suite,
     buildinfo::{self, Buildinfo},
+    version::PackageVersion,
     wb::{BinNMU, SourceSpecifier, WBCommand, WBCommandBuilder},
 };
 use clap::Parser;
+use log::{debug, info};
 
 use crate::{
-    config::{Cache, CacheEntries, CacheState},
+    config::{Cache, CacheEntries},
+    signature::{strip_signature, SignatureOptions, SignatureVerifier, Signer},
     source_packages::SourcePackages,
     BaseOptions, BinNMUsOptions,
 };
@@ -23,11 +26,39 @@ use crate::{
 pub(crate) struct BinNMUBuildinfoOptions {
     #[clap(flatten)]
     binnmu_options: BinNMUsOptions,
+    #[clap(flatten)]
+    signature_options: SignatureOptions,
     /// Input files
     #[clap(parse(from_os_str))]
     inputs: Vec<PathBuf>,
 }
 
+/// A buildinfo file together with the verified signer
+#[derive(Debug)]
+pub(crate) struct SignedBuildinfo {
+    pub(crate) buildinfo: Buildinfo,
+    /// Name of the source package without the version from the `Source` field
+    pub(crate) source: String,
+    pub(crate) signer: Option<Signer>,
+}
+
+impl SignedBuildinfo {
+    fn new(buildinfo: Buildinfo, signer: Option<Signer>) -> Result<Self> {
+        // Source: source (version) includes the version if it differs from the built version
+        let source = buildinfo
+            .source
+            .split_whitespace()
+            .next()
+            .ok_or_else(|| anyhow!("empty Source field"))?
+            .to_owned();
+        Ok(Self {
+            buildinfo,
+            source,
+            signer,
+        })
+    }
+}
+
 pub(crate) struct BinNMUBuildinfo {
     cache: Cache,
     base_options: BaseOptions,
@@ -37,37 +68,77 @@ pub(crate) struct BinNMUBuildinfo {
 impl BinNMUBuildinfo {
     pub(crate) fn new(base_options: BaseOptions, options: BinNMUBuildinfoOptions) -> Result<Self> {
         Ok(Self {
-            cache: Cache::new(base_options.force_download)?,
+            cache: Cache::new(&base_options)?,
             base_options,
             options,
         })
     }
 
     #[tokio::main]
-    async fn download_to_cache(&self) -> Result<CacheState> {
-        self.cache.download(&[CacheEntries::Packages]).await?;
-        Ok(CacheState::FreshFiles)
+    async fn download_releases(&self) -> Result<()> {
+        self.cache.download(&[CacheEntries::Releases]).await?;
+        Ok(())
     }
 
-    fn process(&self, buildinfo: Buildinfo, source_packages: &SourcePackages) -> Result<WBCommand> {
-        let mut source_split = buildinfo.source.split_whitespace();
-        let source_package = source_split.next().unwrap();
+    #[tokio::main]
+    async fn download_to_cache(&self, suite: Suite) -> Result<()> {
+        self.cache
+            .download(&[CacheEntries::SuitePackages(suite)])
+            .await?;
+        Ok(())
+    }
+
+    /// Group buildinfos by source package and version (without binNMU version)
+    pub(crate) fn group_buildinfos(
+        buildinfos: impl IntoIterator<Item = SignedBuildinfo>,
+    ) -> Vec<(String, PackageVersion, Vec<SignedBuildinfo>)> {
+        let mut grouped: HashMap<(String, PackageVersion), Vec<SignedBuildinfo>> = HashMap::new();
+        for buildinfo in buildinfos {
+            let version = buildinfo.buildinfo.version.clone().without_binnmu_version();
+            grouped
+                .entry((buildinfo.source.clone(), version))
+                .or_default()
+                .push(buildinfo);
+        }
 
-        let architectures: Vec<Architecture> = buildinfo
-            .architecture
+        let mut grouped: Vec<_> = grouped
             .into_iter()
-            .filter(|arch| *arch == Architecture::All || *arch == Architecture::Source)
+            .map(|((source, version), buildinfos)| (source, version, buildinfos))
             .collect();
+        grouped.sort_by(|lhs, rhs| lhs.0.cmp(&rhs.0).then_with(|| lhs.1.cmp(&rhs.1)));
+        grouped
+    }
+
+    /// Build a binNMU for all architectures covered by the buildinfos of a source package
+    pub(crate) fn process(
+        source_package: &str,
+        version: &PackageVersion,
+        buildinfos: &[SignedBuildinfo],
+        source_packages: &SourcePackages,
+        options: &BinNMUsOptions,
+    ) -> Result<WBCommand> {
+        let mut architectures: Vec<Architecture> = vec![];
+        for SignedBuildinfo { buildinfo, .. } in buildinfos {
+            if buildinfo.is_cross_build() {
+                debug!(
+                    "{}: cross build on {}",
+                    source_package, buildinfo.build_architecture
+                );
+            }
+            for architecture in buildinfo.binary_architectures() {
+                if !architectures.contains(&architecture) {
+                    architectures.push(architecture);
+                }
+            }
+        }
         if architectures.is_empty() {
             return Err(anyhow!("no binNMU-able architecture"));
         }
+        architectures.sort_by_cached_key(|arch| arch.to_string());
 
         // let mut nmu_version = None;
         let mut source = SourceSpecifier::new(source_package);
-        let version = buildinfo.version.without_binnmu_version();
-        source
-            .with_version(&version)
-            .with_suite(&self.options.binnmu_options.suite);
+        source.with_version(version).with_suite(&options.suite);
         if !source_packages.is_ma_same(source_package) {
             // binNMU only on the architecture if no MA: same binary packages
             source.with_archive_architectures(&architectures);
@@ -79,14 +150,14 @@ impl BinNMUBuildinfo {
             //      }
         }
 
-        let mut binnmu = BinNMU::new(&source, &self.options.binnmu_options.message)?;
-        if let Some(bp) = self.options.binnmu_options.build_priority {
+        let mut binnmu = BinNMU::new(&source, &options.message)?;
+        if let Some(bp) = options.build_priority {
             binnmu.with_build_priority(bp);
         }
-        if let Some(dw) = &self.options.binnmu_options.dep_wait {
+        if let Some(dw) = &options.dep_wait {
             binnmu.with_dependency_wait(dw);
         }
-        if let Some(extra_depends) = &self.options.binnmu_options.extra_depends {
+        if let Some(extra_depends) = &options.extra_depends {
             binnmu.with_extra_depends(extra_depends);
         }
         //  if let Some(version) = nmu_version {
@@ -95,40 +166,87 @@ impl BinNMUBuildinfo {
         Ok(binnmu.build())
     }
 
+    /// Describe who built the binaries covered by a binNMU
+    ///
+    /// Only buildinfos with a verified signer are described.
+    pub(crate) fn describe_signers(buildinfos: &[SignedBuildinfo]) -> Vec<String> {
+        buildinfos
+            .iter()
+            .filter_map(
+                |SignedBuildinfo {
+                     buildinfo,
+                     source,
+                     signer,
+                 }| {
+                    signer.as_ref().map(|signer| {
+                        let architectures: Vec<_> = buildinfo
+                            .architecture
+                            .iter()
+                            .map(ToString::to_string)
+                            .collect();
+                        format!(
+                            "# {}_{} {}: built by {}",
+                            source,
+                            buildinfo.version,
+                            architectures.join(" "),
+                            signer
+                        )
+                    })
+                },
+            )
+            .collect()
+    }
+
     pub(crate) fn run(self) -> Result<()> {
-        self.download_to_cache()?;
-
-        let mut all_paths = vec![];
-        for architecture in RELEASE_ARCHITECTURES {
-            all_paths.push(
-                self.cache
-                    .get_cache_path(format!("Packages_{}", architecture))?,
-            );
-        }
-        let source_packages = SourcePackages::new(&all_paths)?;
+        self.download_releases()?;
+        // versions are checked against the suite the binNMUs are scheduled for
+        let suite = self
+            .cache
+            .resolve_suite(&self.options.binnmu_options.suite)?;
+        self.download_to_cache(suite)?;
+        let package_paths: Vec<_> = self
+            .cache
+            .get_suite_package_paths(suite)?
+            .into_iter()
+            .map(|(_, path)| path)
+            .collect();
+        let source_packages = SourcePackages::new(&package_paths)?;
 
-        let mut wb_commands = HashSet::new();
         // iterate over all buildinfo files
+        let verifier = self.options.signature_options.verifier()?;
+        let mut buildinfos = vec![];
         for filename in &self.options.inputs {
-            let data = strip_signature(BufReader::new(File::open(&filename)?))?;
-            match buildinfo::from_reader(data.as_ref()) {
+            match load_buildinfo(filename, verifier.as_ref()) {
                 Err(e) => {
                     println!("# skipping {}: {}", filename.display(), e);
-                    continue;
                 }
-                Ok(bi) => match self.process(bi, &source_packages) {
-                    Err(e) => {
-                        println!("# skipping {}: {}", filename.display(), e,);
-                        continue;
-                    }
-                    Ok(command) => {
-                        wb_commands.insert(command);
-                    }
-                },
+                Ok(bi) => buildinfos.push(bi),
             }
         }
 
-        for commands in wb_commands {
+        // merge buildinfos of the same source into one binNMU
+        let mut wb_commands = vec![];
+        for (source, version, buildinfos) in Self::group_buildinfos(buildinfos) {
+            match Self::process(
+                &source,
+                &version,
+                &buildinfos,
+                &source_packages,
+                &self.options.binnmu_options,
+            ) {
+                Err(e) => {
+                    println!("# skipping {}_{}: {}", source, version, e);
+                }
+                Ok(command) => {
+                    wb_commands.push((Self::describe_signers(&buildinfos), command));
+                }
+            }
+        }
+
+        for (signers, commands) in wb_commands {
+            for signer in signers {
+                println!("{}", signer);
+            }
             println!("{}", commands);
             if !self.base_options.dry_run {
                 commands.execute()?;
@@ -139,29 +257,128 @@ impl BinNMUBuildinfo {
     }
 }
 
-// Strip the signature from a buildinfo file without verifying it
-fn strip_signature(input: impl BufRead) -> Result<Vec<u8>> {
-    let mut data = vec![];
-    for line in input.lines().skip_while(|rline| {
-        if let Ok(line) = rline {
-            // Skip until the beginning of a buildinfo file
-            !line.starts_with("Format: ")
-        } else {
-            true
-        }
-    }) {
-        if line.is_err() {
-            break;
-        }
+// Load a buildinfo file and check its signature if requested
+//
+// Only the signed part of the file is parsed.
+pub(crate) fn load_buildinfo(
+    path: impl AsRef<Path>,
+    verifier: Option<&SignatureVerifier>,
+) -> Result<SignedBuildinfo> {
+    let data = fs::read_to_string(&path)?;
+    let (text, signer) = match verifier {
+        Some(verifier) => verifier.check(&path, &data)?,
+        None => (strip_signature(&data)?, None),
+    };
+    if let Some(signer) = &signer {
+        info!("{}: signed by {}", path.as_ref().display(), signer);
+    }
+    SignedBuildinfo::new(buildinfo::from_reader(text.as_bytes())?, signer)
+}
+
+#[cfg(test)]
+mod test {
+    use assorted_debian_utils::{
+        archive::{Suite, SuiteOrCodename},
+        buildinfo,
+    };
 
-        let line = line.unwrap();
-        // Read until beginning of the signature block
-        if line.starts_with("-----BEGIN") {
-            return Ok(data);
+    use super::{BinNMUBuildinfo, SignedBuildinfo};
+    use crate::{signature::strip_signature, source_packages::SourcePackages, BinNMUsOptions};
+
+    const PICNIC_I386: &str =
+        include_str!("../assorted-debian-utils/testdata/picnic_3.0.11-1_i386.buildinfo");
+    const RUFF_SOURCE: &str =
+        include_str!("../assorted-debian-utils/testdata/ruff_0.0.291+dfsg1-2_source.buildinfo");
+
+    fn options() -> BinNMUsOptions {
+        BinNMUsOptions {
+            message: "Rebuild against libfoo".into(),
+            build_priority: None,
+            dep_wait: None,
+            extra_depends: None,
+            suite: SuiteOrCodename::Suite(Suite::Unstable),
+            architecture: None,
         }
-        data.write_all(line.as_bytes())?;
-        data.write_all(b"\n")?;
     }
 
-    Ok(data)
+    fn load(data: &str) -> SignedBuildinfo {
+        let data = strip_signature(data).unwrap();
+        SignedBuildinfo::new(buildinfo::from_reader(data.as_bytes()).unwrap(), None).unwrap()
+    }
+
+    #[test]
+    fn unsigned() {
+        assert_eq!(strip_signature(PICNIC_I386).unwrap(), PICNIC_I386);
+    }
+
+    #[test]
+    fn empty_source() {
+        let data = PICNIC_I386.replace("Source: picnic", "Source: ");
+        let buildinfo = buildinfo::from_reader(data.as_bytes()).unwrap();
+        assert!(SignedBuildinfo::new(buildinfo, None).is_err());
+    }
+
+    #[test]
+    fn native_build() {
+        let grouped = BinNMUBuildinfo::group_buildinfos([load(PICNIC_I386), load(RUFF_SOURCE)]);
+        assert_eq!(grouped.len(), 2);
+
+        let (source, version, buildinfos) = &grouped[0];
+        assert_eq!(source, "picnic");
+        assert_eq!(version.to_string(), "3.0.11-1");
+        let command = BinNMUBuildinfo::process(
+            source,
+            version,
+            buildinfos,
+            &SourcePackages::default(),
+            &options(),
+        )
+        .unwrap();
+        assert_eq!(
+            command.to_string(),
+            "nmu picnic_3.0.11-1 . i386 . unstable . -m \"Rebuild against libfoo\""
+        );
+    }
+
+    #[test]
+    fn merge_architectures() {
+        let amd64 = PICNIC_I386.replace("i386", "amd64");
+        let all = PICNIC_I386.replace("Architecture: i386 source", "Architecture: all");
+        let grouped =
+            BinNMUBuildinfo::group_buildinfos([load(&amd64), load(PICNIC_I386), load(&all)]);
+        assert_eq!(grouped.len(), 1);
+
+        let (source, version, buildinfos) = &grouped[0];
+        assert_eq!(source, "picnic");
+        assert_eq!(buildinfos.len(), 3);
+        let command = BinNMUBuildinfo::process(
+            source,
+            version,
+            buildinfos,
+            &SourcePackages::default(),
+            &options(),
+        )
+        .unwrap();
+        assert_eq!(
+            command.to_string(),
+            "nmu picnic_3.0.11-1 . amd64 i386 . unstable . -m \"Rebuild against libfoo\""
+        );
+    }
+
+    #[test]
+    fn source_only() {
+        let grouped = BinNMUBuildinfo::group_buildinfos([load(RUFF_SOURCE)]);
+        assert_eq!(grouped.len(), 1);
+
+        let (source, version, buildinfos) = &grouped[0];
+        assert_eq!(source, "ruff");
+        assert!(BinNMUBuildinfo::process(
+            source,
+            version,
+            buildinfos,
+            &SourcePackages::default(),
+            &options(),
+        )
+        .is_err());
+    }
 }
@@ -0,0 +1,168 @@
+// Copyright 2022 Sebastian Ramacher
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::fs::File;
+
+use anyhow::{anyhow, Result};
+use assorted_debian_utils::{
+    archive::{Suite, SuiteOrCodename},
+    udd_bugs::{self, Filter, SortBy, UDDBug, UDDBugsQuery},
+};
+use clap::Parser;
+
+use crate::{
+    config::{self, CacheEntries},
+    BaseOptions,
+};
+
+#[derive(Debug, Parser)]
+pub(crate) struct BugsOptions {
+    /// Only list bugs affecting this suite or codename
+    #[clap(short, long)]
+    release: Option<SuiteOrCodename>,
+    /// Only list release-critical bugs
+    #[clap(long)]
+    rc: bool,
+    /// Only list bugs with this tag or category, e.g., ftbfs or security
+    #[clap(long = "tag")]
+    tags: Vec<String>,
+    /// Ignore bugs with this tag or category
+    #[clap(long = "ignore-tag")]
+    ignore_tags: Vec<String>,
+    /// Filter bugs tagged patch (include, only or ignore)
+    #[clap(long, default_value = "include")]
+    patch: Filter,
+    /// Filter bugs tagged pending (include, only or ignore)
+    #[clap(long, default_value = "include")]
+    pending: Filter,
+    /// Only list bugs with this usertag, given as user:tag
+    #[clap(long)]
+    usertag: Option<String>,
+    /// Filter merged bugs (include, only or ignore)
+    #[clap(long, default_value = "ignore")]
+    merged: Filter,
+    /// Filter done bugs (include, only or ignore)
+    #[clap(long, default_value = "ignore")]
+    done: Filter,
+    /// Sort bugs by id, source, package, last-modified or severity
+    #[clap(long, default_value = "id")]
+    sort_by: SortBy,
+    /// Sort in descending order
+    #[clap(long)]
+    descending: bool,
+    /// Only list bugs affecting this suite
+    ///
+    /// UDD does not track oldstable, so it cannot be used here.
+    #[clap(long)]
+    affects: Vec<Suite>,
+    /// Only list bugs not affecting this suite
+    ///
+    /// UDD does not track oldstable, so it cannot be used here.
+    #[clap(long)]
+    not_affects: Vec<Suite>,
+    /// Only list bugs of these source packages
+    sources: Vec<String>,
+}
+
+pub(crate) struct Bugs {
+    cache: config::Cache,
+    options: BugsOptions,
+}
+
+impl Bugs {
+    pub(crate) fn new(base_options: BaseOptions, options: BugsOptions) -> Result<Self> {
+        Ok(Self {
+            cache: config::Cache::new(&base_options)?,
+            options,
+        })
+    }
+
+    #[tokio::main]
+    async fn download_releases(&self) -> Result<()> {
+        self.cache.download(&[CacheEntries::Releases]).await?;
+        Ok(())
+    }
+
+    #[tokio::main]
+    async fn download_to_cache(&self, query: &UDDBugsQuery) -> Result<()> {
+        self.cache
+            .download(&[CacheEntries::UDDBugs(query.clone())])
+            .await?;
+        Ok(())
+    }
+
+    fn build_query(&self) -> Result<UDDBugsQuery> {
+        let mut query = UDDBugsQuery::new();
+        if let Some(release) = &self.options.release {
+            self.download_releases()?;
+            query.with_release(&self.cache.resolve_codename(release)?);
+        }
+        if self.options.rc {
+            query.with_rc();
+        }
+        for tag in &self.options.tags {
+            query.with_tag(tag, Filter::Only)?;
+        }
+        for tag in &self.options.ignore_tags {
+            query.with_tag(tag, Filter::Ignore)?;
+        }
+        if self.options.patch != Filter::Include {
+            query.with_patch(self.options.patch);
+        }
+        if self.options.pending != Filter::Include {
+            query.with_pending(self.options.pending);
+        }
+        if let Some(usertag) = &self.options.usertag {
+            let (user, tag) = usertag
+                .split_once(':')
+                .ok_or_else(|| anyhow!("invalid usertag: {}", usertag))?;
+            query.with_usertag(user, tag);
+        }
+        query
+            .with_merged(self.options.merged)
+            .with_done(self.options.done)
+            .with_sort(self.options.sort_by, self.options.descending);
+        Ok(query)
+    }
+
+    // Reject suites not tracked by UDD
+    fn check_suites(&self) -> Result<()> {
+        let untracked = self
+            .options
+            .affects
+            .iter()
+            .chain(self.options.not_affects.iter())
+            .find(|suite| matches!(suite, Suite::OldStable(_)));
+        match untracked {
+            Some(suite) => Err(anyhow!("UDD does not track whether bugs affect {}", suite)),
+            None => Ok(()),
+        }
+    }
+
+    fn is_selected(&self, bug: &UDDBug) -> bool {
+        (self.options.sources.is_empty() || self.options.sources.contains(&bug.source))
+            && self
+                .options
+                .affects
+                .iter()
+                .all(|suite| bug.affects(*suite) == Some(true))
+            && !self
+                .options
+                .not_affects
+                .iter()
+                .any(|suite| bug.affects(*suite) == Some(true))
+    }
+
+    pub(crate) fn run(self) -> Result<()> {
+        self.check_suites()?;
+        let query = self.build_query()?;
+        self.download_to_cache(&query)?;
+
+        let bugs = udd_bugs::from_reader(File::open(self.cache.get_udd_bugs_path(&query)?)?)?;
+        for bug in bugs.iter().filter(|bug| self.is_selected(bug)) {
+            println!("{}", bug);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,232 @@
+// Copyright 2022 Sebastian Ramacher
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Result;
+use assorted_debian_utils::{
+    architectures::Architecture, archive::Suite, buildinfo::Buildinfo, relations::VersionConstraint,
+};
+use clap::Parser;
+use indicatif::{ProgressBar, ProgressIterator};
+use log::{debug, info, trace, warn};
+
+use crate::{
+    binnmu_buildinfo::{load_buildinfo, BinNMUBuildinfo, SignedBuildinfo},
+    config::{self, Cache, CacheEntries},
+    signature::SignatureOptions,
+    source_packages::SourcePackages,
+    BaseOptions, BinNMUsOptions,
+};
+
+#[derive(Debug, Parser)]
+pub(crate) struct BuiltAgainstOptions {
+    #[clap(flatten)]
+    binnmu_options: BinNMUsOptions,
+    #[clap(flatten)]
+    signature_options: SignatureOptions,
+    /// Package from Installed-Build-Depends to search for
+    #[clap(long)]
+    package: String,
+    /// Version constraint for the package, e.g., '= 12.2.0-3'. If specified multiple times, all constraints need to be satisfied.
+    #[clap(long = "constraint")]
+    constraints: Vec<VersionConstraint>,
+    /// Directories to search for buildinfo files
+    #[clap(parse(from_os_str), required = true)]
+    directories: Vec<PathBuf>,
+}
+
+pub(crate) struct BuiltAgainst {
+    cache: Cache,
+    base_options: BaseOptions,
+    options: BuiltAgainstOptions,
+}
+
+// Recursively collect all buildinfo files
+//
+// Symbolic links to directories are not followed to avoid running into cycles.
+fn collect_buildinfos(path: &Path, buildinfos: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            collect_buildinfos(&path, buildinfos)?;
+        } else if path.extension().is_some_and(|ext| ext == "buildinfo") {
+            buildinfos.push(path);
+        }
+    }
+    Ok(())
+}
+
+impl BuiltAgainst {
+    pub(crate) fn new(base_options: BaseOptions, options: BuiltAgainstOptions) -> Result<Self> {
+        Ok(Self {
+            cache: Cache::new(&base_options)?,
+            base_options,
+            options,
+        })
+    }
+
+    #[tokio::main]
+    async fn download_releases(&self) -> Result<()> {
+        self.cache.download(&[CacheEntries::Releases]).await?;
+        Ok(())
+    }
+
+    #[tokio::main]
+    async fn download_to_cache(&self, suite: Suite) -> Result<()> {
+        self.cache
+            .download(&[CacheEntries::SuitePackages(suite)])
+            .await?;
+        Ok(())
+    }
+
+    fn is_built_against(&self, buildinfo: &Buildinfo) -> bool {
+        match buildinfo.installed_version(&self.options.package) {
+            Some(version) => self
+                .options
+                .constraints
+                .iter()
+                .all(|constraint| constraint.is_satisfied_by(version)),
+            None => false,
+        }
+    }
+
+    pub(crate) fn run(self) -> Result<()> {
+        self.download_releases()?;
+        // only consider versions available in the suite the binNMUs are scheduled for
+        let suite = self
+            .cache
+            .resolve_suite(&self.options.binnmu_options.suite)?;
+        self.download_to_cache(suite)?;
+        let package_paths: Vec<_> = self
+            .cache
+            .get_suite_package_paths(suite)?
+            .into_iter()
+            .map(|(_, path)| path)
+            .collect();
+        let source_packages = SourcePackages::new(&package_paths)?;
+
+        let mut paths = vec![];
+        for directory in &self.options.directories {
+            collect_buildinfos(directory, &mut paths)?;
+        }
+        paths.sort();
+
+        let pb = ProgressBar::new(paths.len() as u64);
+        pb.set_style(config::default_progress_style().template(
+            "{msg}: {spinner:.green} [{wide_bar:.cyan/blue}] {pos}/{len} ({per_sec}, {eta})",
+        ));
+        pb.set_message("Processing buildinfos");
+
+        // deduplicate by source and architecture, only keeping the most recent build
+        let verifier = self.options.signature_options.verifier()?;
+        let mut buildinfos: HashMap<(String, Vec<Architecture>), SignedBuildinfo> = HashMap::new();
+        for path in paths.iter().progress_with(pb) {
+            trace!("Processing {}", path.display());
+            let signed_buildinfo = match load_buildinfo(path, verifier.as_ref()) {
+                Ok(signed_buildinfo) => signed_buildinfo,
+                Err(e) => {
+                    warn!("Skipping {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            let buildinfo = &signed_buildinfo.buildinfo;
+            if !self.is_built_against(buildinfo) {
+                continue;
+            }
+
+            let source = signed_buildinfo.source.clone();
+            let version = buildinfo.version.clone().without_binnmu_version();
+            if !source_packages.has_version(&source, &version) {
+                info!(
+                    "Skipping {}: {} {} is not in {}",
+                    path.display(),
+                    source,
+                    version,
+                    suite
+                );
+                continue;
+            }
+
+            // source-only and arch:all parts do not affect which architectures are binNMUed
+            match buildinfos.entry((source, buildinfo.binary_architectures())) {
+                Entry::Occupied(mut entry) => {
+                    if entry.get().buildinfo.version < buildinfo.version {
+                        debug!(
+                            "Replacing {} by {}",
+                            entry.get().buildinfo.version,
+                            buildinfo.version
+                        );
+                        entry.insert(signed_buildinfo);
+                    }
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(signed_buildinfo);
+                }
+            }
+        }
+
+        // merge buildinfos of the same source into one binNMU
+        let mut wb_commands = vec![];
+        for (source, version, buildinfos) in
+            BinNMUBuildinfo::group_buildinfos(buildinfos.into_values())
+        {
+            match BinNMUBuildinfo::process(
+                &source,
+                &version,
+                &buildinfos,
+                &source_packages,
+                &self.options.binnmu_options,
+            ) {
+                Err(e) => {
+                    println!("# skipping {}_{}: {}", source, version, e);
+                }
+                Ok(command) => {
+                    wb_commands.push((BinNMUBuildinfo::describe_signers(&buildinfos), command));
+                }
+            }
+        }
+
+        for (signers, commands) in wb_commands {
+            for signer in signers {
+                println!("{}", signer);
+            }
+            println!("{}", commands);
+            if !self.base_options.dry_run {
+                commands.execute()?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::{self, File};
+
+    use super::collect_buildinfos;
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_cycle() {
+        let directory = tempfile::tempdir().unwrap();
+        let subdirectory = directory.path().join("z").join("zathura");
+        fs::create_dir_all(&subdirectory).unwrap();
+        File::create(subdirectory.join("zathura_0.4.9-1_amd64.buildinfo")).unwrap();
+        File::create(subdirectory.join("zathura_0.4.9-1.dsc")).unwrap();
+        std::os::unix::fs::symlink(directory.path(), subdirectory.join("loop")).unwrap();
+
+        let mut buildinfos = vec![];
+        collect_buildinfos(directory.path(), &mut buildinfos).unwrap();
+        assert_eq!(
+            buildinfos,
+            vec![subdirectory.join("zathura_0.4.9-1_amd64.buildinfo")]
+        );
+    }
+}
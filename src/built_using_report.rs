@@ -0,0 +1,97 @@
+// Copyright 2022 Sebastian Ramacher
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use assorted_debian_utils::{
+    archive::Suite,
+    outdated_built_using::{self, OutdatedBuiltUsing},
+};
+use clap::Parser;
+use log::warn;
+
+use crate::{
+    config::{self, CacheEntries},
+    BaseOptions,
+};
+
+#[derive(Debug, Parser)]
+pub(crate) struct BuiltUsingReportOptions {
+    /// Only report entries for the given suite
+    #[clap(short, long)]
+    suite: Option<Suite>,
+    /// Only report the given embedded source packages
+    built_using_sources: Vec<String>,
+}
+
+pub(crate) struct BuiltUsingReport {
+    cache: config::Cache,
+    options: BuiltUsingReportOptions,
+}
+
+impl BuiltUsingReport {
+    pub(crate) fn new(base_options: BaseOptions, options: BuiltUsingReportOptions) -> Result<Self> {
+        Ok(Self {
+            cache: config::Cache::new(&base_options)?,
+            options,
+        })
+    }
+
+    #[tokio::main]
+    async fn download_to_cache(&self) -> Result<()> {
+        self.cache
+            .download(&[CacheEntries::OutdatedBuiltUsing])
+            .await?;
+        Ok(())
+    }
+
+    fn is_selected(&self, entry: &OutdatedBuiltUsing) -> bool {
+        if let Some(suite) = &self.options.suite {
+            if entry.suite != *suite {
+                return false;
+            }
+        }
+        self.options.built_using_sources.is_empty()
+            || self
+                .options
+                .built_using_sources
+                .contains(&entry.built_using_source)
+    }
+
+    pub(crate) fn run(self) -> Result<()> {
+        self.download_to_cache()?;
+
+        let mut entries = vec![];
+        for entry in outdated_built_using::from_reader(
+            self.cache.get_cache_bufreader("outdated-built-using.txt")?,
+        ) {
+            match entry {
+                Ok(entry) => entries.push(entry),
+                Err(err) => warn!("Unable to process entry: {}", err),
+            }
+        }
+
+        // group by the embedded source package
+        let mut grouped: BTreeMap<&str, Vec<&OutdatedBuiltUsing>> = BTreeMap::new();
+        for entry in entries.iter().filter(|entry| self.is_selected(entry)) {
+            grouped
+                .entry(entry.built_using_source.as_str())
+                .or_default()
+                .push(entry);
+        }
+
+        for (built_using_source, mut entries) in grouped {
+            entries.sort_by(|lhs, rhs| lhs.source.cmp(&rhs.source));
+            println!("{} ({} packages)", built_using_source, entries.len());
+            for entry in entries {
+                println!(
+                    "  {} {} ({}): built using {}",
+                    entry.suite, entry.source, entry.version, entry.built_using_version
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
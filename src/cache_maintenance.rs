@@ -0,0 +1,199 @@
+// Copyright 2022 Sebastian Ramacher
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::{
+    fs,
+    path::Path,
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{anyhow, Result};
+use assorted_debian_utils::fetch::Metadata;
+use clap::{Parser, Subcommand};
+use log::info;
+
+use crate::{
+    config::{Cache, CacheEntries},
+    BaseOptions,
+};
+
+/// Age of a cached file, e.g., `30m`, `12h`, `7d` or `2w`
+///
+/// Without a unit, the age is given in days.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Age(Duration);
+
+impl FromStr for Age {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let (value, unit) = s.split_at(split);
+        let value: u64 = value.parse().map_err(|_| anyhow!("invalid age: {}", s))?;
+        let factor = match unit {
+            "s" => 1,
+            "m" => 60,
+            "h" => 60 * 60,
+            "" | "d" => 24 * 60 * 60,
+            "w" => 7 * 24 * 60 * 60,
+            _ => return Err(anyhow!("invalid unit of age: {}", unit)),
+        };
+        let seconds = value
+            .checked_mul(factor)
+            .ok_or_else(|| anyhow!("age is too large: {}", s))?;
+        Ok(Age(Duration::from_secs(seconds)))
+    }
+}
+
+#[derive(Debug, Subcommand)]
+enum CacheCommands {
+    /// List cached files with their size, modification date and source URL
+    List,
+    /// Download entries again even if they are up to date
+    ///
+    /// Entries are excuses, packages, releases, auto-removals, outdated-built-using,
+    /// packages:<suite>, ftbfs-bugs:<suite>, contents:<suite>, contents:<suite>:<architecture>
+    /// and udd-bugs:<query>.
+    Refresh {
+        /// Entries to refresh
+        #[clap(required = true)]
+        entries: Vec<CacheEntries>,
+    },
+    /// Remove cached files
+    Clean {
+        /// Only remove files downloaded longer ago than the given age, e.g., 12h, 7d or 2w
+        #[clap(long)]
+        older_than: Option<Age>,
+    },
+    /// Print the path of the cache directory
+    Path,
+}
+
+#[derive(Debug, Parser)]
+pub(crate) struct CacheMaintenanceOptions {
+    #[clap(subcommand)]
+    command: CacheCommands,
+}
+
+pub(crate) struct CacheMaintenance {
+    cache: Cache,
+    options: CacheMaintenanceOptions,
+}
+
+// Modification time of a cached file
+fn modified(path: &Path) -> Result<SystemTime> {
+    Ok(fs::metadata(path)?.modified()?)
+}
+
+// Time a cached file was downloaded
+//
+// The modification time is the server's Last-Modified date, so it is only used for files without
+// the download time in their metadata.
+fn downloaded(path: &Path) -> Result<SystemTime> {
+    match Metadata::load(path).and_then(|metadata| metadata.downloaded) {
+        Some(downloaded) => Ok(downloaded.into()),
+        None => modified(path),
+    }
+}
+
+impl CacheMaintenance {
+    pub(crate) fn new(base_options: BaseOptions, options: CacheMaintenanceOptions) -> Result<Self> {
+        Ok(Self {
+            cache: Cache::new(&base_options)?,
+            options,
+        })
+    }
+
+    #[tokio::main]
+    async fn refresh(&self, entries: &[CacheEntries]) -> Result<()> {
+        self.cache.refresh(entries).await?;
+        Ok(())
+    }
+
+    fn list(&self) -> Result<()> {
+        for path in self.cache.cached_files()? {
+            let metadata = Metadata::load(&path).unwrap_or_default();
+            let last_modified = match metadata.last_modified {
+                Some(last_modified) => last_modified,
+                None => httpdate::fmt_http_date(modified(&path)?),
+            };
+            println!(
+                "{:<40} {:>12} {:<29} {}",
+                path.file_name().unwrap_or_default().to_string_lossy(),
+                fs::metadata(&path)?.len(),
+                last_modified,
+                metadata.url
+            );
+        }
+        Ok(())
+    }
+
+    fn clean(&self, older_than: Option<Age>) -> Result<()> {
+        let now = SystemTime::now();
+        for path in self.cache.cached_files()? {
+            if let Some(Age(age)) = older_than {
+                // files with a download date in the future are kept
+                if now.duration_since(downloaded(&path)?).unwrap_or_default() <= age {
+                    continue;
+                }
+            }
+            info!("Removing {}", path.display());
+            self.cache.remove_cached_file(&path)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn run(self) -> Result<()> {
+        match &self.options.command {
+            CacheCommands::List => self.list(),
+            CacheCommands::Refresh { entries } => self.refresh(entries),
+            CacheCommands::Clean { older_than } => self.clean(*older_than),
+            CacheCommands::Path => {
+                println!("{}", self.cache.cache_directory().display());
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::Age;
+    use crate::config::CacheEntries;
+
+    #[test]
+    fn age() {
+        assert_eq!("90s".parse::<Age>().unwrap(), Age(Duration::from_secs(90)));
+        assert_eq!(
+            "12h".parse::<Age>().unwrap(),
+            Age(Duration::from_secs(12 * 3600))
+        );
+        assert_eq!("7".parse::<Age>().unwrap(), "7d".parse::<Age>().unwrap());
+        assert_eq!("1w".parse::<Age>().unwrap(), "7d".parse::<Age>().unwrap());
+        assert!("d".parse::<Age>().is_err());
+        assert!("7y".parse::<Age>().is_err());
+        assert!(format!("{}w", u64::MAX).parse::<Age>().is_err());
+    }
+
+    #[test]
+    fn cache_entries() {
+        for entry in [
+            "excuses",
+            "packages",
+            "packages:stable",
+            "ftbfs-bugs:testing",
+            "contents:stable",
+            "contents:unstable:amd64",
+            "contents:testing:arm64",
+            "ftbfs-bugs:unstable",
+            "udd-bugs:release=sid&rc=1&ftbfs=only&merged=ign&done=ign&sortby=id&sorto=asc",
+        ] {
+            assert_eq!(entry.parse::<CacheEntries>().unwrap().to_string(), entry);
+        }
+        assert!("contents".parse::<CacheEntries>().is_err());
+        assert!("foo:unstable".parse::<CacheEntries>().is_err());
+    }
+}
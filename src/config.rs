@@ -2,22 +2,38 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
     fs::{self, File},
-    io::{BufReader, BufWriter, Write},
+    io::{BufRead, BufReader, BufWriter, Write},
     path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Arc, Mutex, OnceLock},
 };
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use assorted_debian_utils::{
-    architectures::{Architecture, RELEASE_ARCHITECTURES},
-    archive::{Codename, Suite},
+    architectures::Architecture,
+    archive::{Checksum, Suite, SuiteOrCodename},
+    contents_diff::Partition,
+    fetch::{
+        self, DirectoryStore, Download, Fetcher, Metadata, Progress, ReqwestBackend, State,
+        AUTOREMOVALS_URL, EXCUSES_URL, METADATA_SUFFIX,
+    },
+    release::{self, Release, SuiteMapping},
+    udd_bugs::{Filter, UDDBugsQuery},
 };
-use futures_util::StreamExt;
-use indicatif::{ProgressBar, ProgressStyle};
-use log::debug;
-use reqwest::{header, Client, Response, StatusCode};
+use futures_util::{stream, StreamExt};
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use log::{debug, warn};
+use rayon::prelude::*;
+use serde::{de::DeserializeOwned, Serialize};
 use xdg::BaseDirectories;
-use xz2::write::XzDecoder;
+
+use crate::{
+    signature::{Keyring, Signature, SignedText},
+    BaseOptions,
+};
 
 const PROGRESS_CHARS: &str = "█  ";
 
@@ -25,15 +41,169 @@ pub(crate) fn default_progress_style() -> ProgressStyle {
     ProgressStyle::default_bar().progress_chars(PROGRESS_CHARS)
 }
 
-#[derive(Debug, Clone, Copy)]
+/// Open a cached file and report the progress of reading it
+///
+/// The returned progress bar should be finished once the reader is exhausted.
+fn open_with_progress(path: &Path) -> Result<(ProgressBar, impl BufRead + Send)> {
+    let file = File::open(path)?;
+    let pb = ProgressBar::new(file.metadata()?.len());
+    pb.set_style(default_progress_style().template(
+        "{msg}: {spinner:.green} [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})",
+    ));
+    pb.set_message(format!(
+        "Processing {}",
+        path.file_name().unwrap_or_default().to_string_lossy()
+    ));
+    let reader = BufReader::new(pb.wrap_read(file));
+    Ok((pb, reader))
+}
+
+/// Process cached files in parallel and report the progress of reading them
+///
+/// The results are stored as pre-parsed data named `name` next to each file and are reused as
+/// long as the file does not change. The results are returned in the order of the given paths.
+pub(crate) fn process_in_parallel<P, T, F>(paths: &[P], name: &str, f: F) -> Result<Vec<T>>
+where
+    P: AsRef<Path> + Sync,
+    T: Send + Serialize + DeserializeOwned,
+    F: Fn(&mut dyn BufRead) -> Result<T> + Sync,
+{
+    // reuse the pre-parsed data of unchanged files
+    let mut results: Vec<Option<T>> = paths
+        .par_iter()
+        .map(|path| ParsedIndex::load(path.as_ref(), name))
+        .collect();
+
+    let mp = MultiProgress::new();
+    let readers = paths
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| results[*index].is_none())
+        .map(|(index, path)| {
+            let (pb, reader) = open_with_progress(path.as_ref())?;
+            Ok((index, path.as_ref(), mp.add(pb), reader))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    // the progress bars are only drawn while the multi progress bar is joined
+    let drawer = std::thread::spawn(move || mp.join());
+
+    // process all files, so that all progress bars are finished
+    let parsed: Vec<(usize, Result<T>)> = readers
+        .into_par_iter()
+        .map(|(index, path, pb, mut reader)| {
+            let result = f(&mut reader);
+            pb.finish();
+            if let Ok(data) = &result {
+                if let Err(err) = ParsedIndex::store(path, name, data) {
+                    warn!(
+                        "Unable to store pre-parsed data of {}: {}",
+                        path.display(),
+                        err
+                    );
+                }
+            }
+            (index, result)
+        })
+        .collect();
+    drawer
+        .join()
+        .map_err(|_| anyhow!("unable to draw progress bars"))??;
+
+    for (index, result) in parsed {
+        results[index] = Some(result?);
+    }
+    Ok(results.into_iter().flatten().collect())
+}
+
+// Amount of Contents data that is kept in memory at once
+const PARTITION_SIZE: u64 = 512 * 1024 * 1024;
+
+/// Split the paths of cached Contents files into partitions that can be kept in memory
+///
+/// The partitions are sized so that the entries of all the given files belonging to one
+/// partition amount to at most `PARTITION_SIZE` bytes of Contents data.
+pub(crate) fn contents_partitions<P>(paths: &[P]) -> Result<Vec<Partition>>
+where
+    P: AsRef<Path>,
+{
+    let mut size = 0;
+    for path in paths {
+        size += fs::metadata(path)?.len();
+    }
+    Ok(Partition::split(size.div_ceil(PARTITION_SIZE)).collect())
+}
+
+// Suites for which the release files are cached
+const RELEASE_SUITES: [Suite; 5] = [
+    Suite::Unstable,
+    Suite::Testing(None),
+    Suite::Stable(None),
+    Suite::OldStable(None),
+    Suite::Experimental,
+];
+
+const ARCHIVE_KEYRING: &str = "/usr/share/keyrings/debian-archive-keyring.gpg";
+
+#[derive(Debug, Clone)]
 pub(crate) enum CacheEntries {
     Excuses,
     Packages,
-    FTBFSBugs(Codename),
+    SuitePackages(Suite),
+    Releases,
+    FTBFSBugs(SuiteOrCodename),
     AutoRemovals,
     OutdatedBuiltUsing,
+    UDDBugs(UDDBugsQuery),
     // Sources,
     Contents(Suite),
+    ArchitectureContents(Suite, Architecture),
+}
+
+impl Display for CacheEntries {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            CacheEntries::Excuses => write!(f, "excuses"),
+            CacheEntries::Packages => write!(f, "packages"),
+            CacheEntries::SuitePackages(suite) => write!(f, "packages:{}", suite),
+            CacheEntries::Releases => write!(f, "releases"),
+            CacheEntries::FTBFSBugs(suite) => write!(f, "ftbfs-bugs:{}", suite),
+            CacheEntries::AutoRemovals => write!(f, "auto-removals"),
+            CacheEntries::OutdatedBuiltUsing => write!(f, "outdated-built-using"),
+            CacheEntries::UDDBugs(query) => write!(f, "udd-bugs:{}", query),
+            CacheEntries::Contents(suite) => write!(f, "contents:{}", suite),
+            CacheEntries::ArchitectureContents(suite, architecture) => {
+                write!(f, "contents:{}:{}", suite, architecture)
+            }
+        }
+    }
+}
+
+impl FromStr for CacheEntries {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.split_once(':') {
+            None => match s {
+                "excuses" => Ok(CacheEntries::Excuses),
+                "packages" => Ok(CacheEntries::Packages),
+                "releases" => Ok(CacheEntries::Releases),
+                "auto-removals" => Ok(CacheEntries::AutoRemovals),
+                "outdated-built-using" => Ok(CacheEntries::OutdatedBuiltUsing),
+                _ => Err(anyhow!("unknown cache entry: {}", s)),
+            },
+            Some(("ftbfs-bugs", suite)) => Ok(CacheEntries::FTBFSBugs(suite.parse()?)),
+            Some(("packages", suite)) => Ok(CacheEntries::SuitePackages(suite.parse()?)),
+            Some(("contents", value)) => match value.split_once(':') {
+                Some((suite, architecture)) => Ok(CacheEntries::ArchitectureContents(
+                    suite.parse()?,
+                    architecture.parse()?,
+                )),
+                None => Ok(CacheEntries::Contents(value.parse()?)),
+            },
+            Some(("udd-bugs", query)) => Ok(CacheEntries::UDDBugs(query.parse()?)),
+            Some(_) => Err(anyhow!("unknown cache entry: {}", s)),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
@@ -42,233 +212,441 @@ pub(crate) enum CacheState {
     FreshFiles,
 }
 
-#[derive(Debug)]
-struct Downloader {
-    always_download: bool,
-    client: Client,
+// Report the progress of a download with a progress bar
+struct DownloadProgress<'a>(&'a ProgressBar);
+
+impl Progress for DownloadProgress<'_> {
+    fn start(&self, url: &str, length: Option<u64>) {
+        debug!("Starting download of {}", url);
+        self.0.set_length(length.unwrap_or(0));
+        self.0.set_message(format!("Downloading {}", url));
+    }
+
+    fn advance(&self, bytes: u64) {
+        self.0.inc(bytes);
+    }
+
+    fn retry(&self, url: &str, error: &fetch::Error) {
+        warn!("Download of {} failed, retrying: {}", url, error);
+        self.0.reset();
+        self.0.set_message(format!("Retrying {}", url));
+    }
 }
 
-impl Downloader {
-    pub fn new(always_download: bool) -> Self {
-        Self {
-            always_download,
-            client: Client::new(),
-        }
+/// Pre-parsed data of a cached file
+///
+/// The data is stored next to the cached file as `{file}.{name}.parsed` together with the SHA256
+/// checksum of the download it was parsed from. If the checksum does not match the one of the
+/// current download, the data is ignored.
+pub(crate) struct ParsedIndex;
+
+const PARSED_SUFFIX: &str = ".parsed";
+// bump if the layout of any of the pre-parsed data changes
+const PARSED_FORMAT_VERSION: u32 = 3;
+
+impl ParsedIndex {
+    // Path of the file storing the pre-parsed data of a cached file
+    fn path(path: &Path, name: &str) -> PathBuf {
+        let mut path = path.as_os_str().to_owned();
+        path.push(format!(".{}{}", name, PARSED_SUFFIX));
+        PathBuf::from(path)
     }
 
-    async fn download_init<P>(&self, url: &str, path: P) -> Result<Option<(Response, ProgressBar)>>
+    /// Load the pre-parsed data of a cached file if the file did not change
+    pub fn load<T>(path: &Path, name: &str) -> Option<T>
     where
-        P: AsRef<Path>,
+        T: DeserializeOwned,
     {
-        debug!("Starting download of {} to {:?}", url, path.as_ref());
-        let res = self.client.get(url);
-        let res = if !self.always_download {
-            if let Ok(dst_metadata) = fs::metadata(path) {
-                // if always_download was not set and we have local copy, tell the server the date
-                res.header(
-                    header::IF_MODIFIED_SINCE,
-                    httpdate::fmt_http_date(dst_metadata.modified()?),
-                )
-            } else {
-                res
-            }
-        } else {
-            res
-        }
-        .send()
-        .await
-        .with_context(|| format!("Failed to GET from '{}'", &url))?;
-
-        if !self.always_download && res.status() == StatusCode::NOT_MODIFIED {
-            // this will only trigger if always_download is not set and the server reports that the
-            // file was not modified
-            debug!(
-                "Skipping {}: always_download is not set and the file was not modified",
-                url
-            );
-            return Ok(None);
-        }
-
-        if let Some(total_size) = res.content_length() {
-            let pb = ProgressBar::new(total_size);
-            pb.set_style(default_progress_style()
-            .template("{msg}: {spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")
-            );
-            pb.set_message(format!("Downloading {}", url));
-            Ok(Some((res, pb)))
-        } else {
-            Ok(Some((res, ProgressBar::hidden())))
+        let sha256 = Metadata::load(path)?.sha256?;
+        let file = File::open(Self::path(path, name)).ok()?;
+        let mut reader = BufReader::new(file);
+        let (version, parsed_sha256): (u32, String) =
+            bincode::deserialize_from(&mut reader).ok()?;
+        if version != PARSED_FORMAT_VERSION || parsed_sha256 != sha256 {
+            debug!("Pre-parsed data of {} is outdated", path.display());
+            return None;
         }
-    }
-
-    async fn download_internal(
-        &self,
-        res: Response,
-        pb: &ProgressBar,
-        writer: &mut impl Write,
-    ) -> Result<()> {
-        let mut stream = res.bytes_stream();
-        while let Some(item) = stream.next().await {
-            let chunk = item.with_context(|| "Error while downloading file".to_string())?;
-            writer
-                .write_all(&chunk)
-                .with_context(|| "Error while writing to file".to_string())?;
-            pb.inc(chunk.len() as u64);
+        match bincode::deserialize_from(reader) {
+            Ok(data) => {
+                debug!("Using pre-parsed data of {}", path.display());
+                Some(data)
+            }
+            Err(err) => {
+                warn!(
+                    "Unable to load pre-parsed data of {}: {}",
+                    path.display(),
+                    err
+                );
+                None
+            }
         }
-        Ok(())
     }
 
-    pub async fn download_file<P>(&self, url: &str, path: P) -> Result<CacheState>
+    /// Store the pre-parsed data of a cached file
+    ///
+    /// Nothing is stored if the checksum of the file is unknown.
+    pub fn store<T>(path: &Path, name: &str, data: &T) -> Result<()>
     where
-        P: AsRef<Path>,
+        T: Serialize,
     {
-        let res = self.download_init(url, &path).await?;
-        if res.is_none() {
-            return Ok(CacheState::NoUpdate);
+        let sha256 = match Metadata::load(path).and_then(|metadata| metadata.sha256) {
+            Some(sha256) => sha256,
+            None => return Ok(()),
+        };
+
+        let parsed_path = Self::path(path, name);
+        let mut file = tempfile::NamedTempFile::new_in(
+            parsed_path.parent().unwrap_or_else(|| Path::new(".")),
+        )?;
+        {
+            let mut writer = BufWriter::new(file.as_file_mut());
+            bincode::serialize_into(&mut writer, &(PARSED_FORMAT_VERSION, sha256))?;
+            bincode::serialize_into(&mut writer, data)?;
+            writer.flush()?;
         }
+        file.persist(parsed_path)?;
+        Ok(())
+    }
 
-        let (res, pb) = res.unwrap();
-        let mut file = File::create(&path)
-            .with_context(|| format!("Failed to create file '{}'", path.as_ref().display()))?;
-        if url.ends_with(".xz") {
-            self.download_internal(res, &pb, &mut XzDecoder::new(file))
-                .await?;
-        } else if url.ends_with(".gz") {
-            let mut writer = flate2::write::GzDecoder::new(file);
-            self.download_internal(res, &pb, &mut writer).await?;
-            writer.try_finish()?;
-        } else {
-            self.download_internal(res, &pb, &mut file).await?;
+    /// Remove all pre-parsed data of a cached file
+    fn remove_all(path: &Path) -> Result<()> {
+        let (directory, file_name) = match (path.parent(), path.file_name()) {
+            (Some(directory), Some(file_name)) => (directory, file_name.to_string_lossy()),
+            _ => return Ok(()),
+        };
+        let prefix = format!("{}.", file_name);
+        for entry in fs::read_dir(directory)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if name.starts_with(&prefix) && name.ends_with(PARSED_SUFFIX) {
+                fs::remove_file(entry.path())?;
+            }
         }
-        pb.finish_with_message(format!("Downloaded {}", url));
-        debug!("Download of {} to {:?} done", url, path.as_ref());
-        Ok(CacheState::FreshFiles)
+        Ok(())
     }
 }
 
 #[derive(Debug)]
 pub(crate) struct Cache {
     base_directory: BaseDirectories,
-    downloader: Downloader,
+    fetcher: Fetcher<ReqwestBackend, DirectoryStore>,
+    parallel_downloads: usize,
+    verify_release: bool,
+    archive_keyring: OnceLock<Keyring>,
+    // release files and the suite mapping are loaded once and dropped if the release files are
+    // downloaded again
+    releases: Mutex<HashMap<Suite, Arc<Release>>>,
+    suite_mapping: Mutex<Option<Arc<SuiteMapping>>>,
 }
 
 impl Cache {
-    pub fn new(force_download: bool) -> Result<Self> {
+    pub fn new(base_options: &BaseOptions) -> Result<Self> {
+        let base_directory = BaseDirectories::with_prefix("Debian-RT-tools")?;
+        let fetcher = Fetcher::new(
+            ReqwestBackend::default(),
+            DirectoryStore::new(base_directory.get_cache_home())?,
+        )
+        .with_always_download(base_options.force_download);
         Ok(Self {
-            base_directory: BaseDirectories::with_prefix("Debian-RT-tools")?,
-            downloader: Downloader::new(force_download),
+            base_directory,
+            fetcher,
+            parallel_downloads: base_options.parallel_downloads.max(1),
+            verify_release: base_options.verify_release,
+            archive_keyring: OnceLock::new(),
+            releases: Mutex::default(),
+            suite_mapping: Mutex::default(),
         })
     }
 
-    async fn download_excuses(&self) -> Result<CacheState> {
-        self.downloader
-            .download_file(
-                "https://release.debian.org/britney/excuses.yaml",
-                self.get_cache_path("excuses.yaml")?,
-            )
-            .await
+    fn release_download(&self, suite: Suite) -> Download {
+        Download::new(
+            self.fetcher.archive_url(suite, "InRelease"),
+            format!("InRelease_{}", suite),
+        )
     }
 
-    async fn download_contents(&self, suite: Suite) -> Result<CacheState> {
-        let mut state = CacheState::NoUpdate;
-        for architecture in RELEASE_ARCHITECTURES
+    fn contents_download(
+        &self,
+        release: &Release,
+        suite: Suite,
+        architecture: &Architecture,
+    ) -> Result<Download> {
+        let file = format!("main/Contents-{}.gz", architecture);
+        Ok(Download::new(
+            self.fetcher.archive_url(suite, &file),
+            Self::contents_name(suite, architecture),
+        )
+        .with_checksum(Self::expected_checksum(release, &file)?))
+    }
+
+    fn contents_downloads(&self, suite: Suite) -> Result<Vec<Download>> {
+        let release = self.release(suite)?;
+        release
+            .binary_architectures()
             .into_iter()
-            .chain([Architecture::All].into_iter())
-        {
-            let url = format!(
-                "https://deb.debian.org/debian/dists/{}/main/Contents-{}.gz",
-                suite, architecture
-            );
-            let dest = format!("Contents_{}_{}", suite, architecture);
-            if self
-                .downloader
-                .download_file(&url, self.get_cache_path(&dest)?)
-                .await?
-                == CacheState::FreshFiles
-            {
-                state = CacheState::FreshFiles;
-            }
+            .chain([Architecture::All])
+            .map(|architecture| self.contents_download(&release, suite, &architecture))
+            .collect()
+    }
+
+    // Name of the cached Contents file of a suite
+    fn contents_name(suite: Suite, architecture: &Architecture) -> String {
+        format!("Contents_{}_{}", suite, architecture)
+    }
+
+    // Name of the cached Packages file of a suite
+    fn packages_name(suite: Suite, architecture: &Architecture) -> String {
+        match suite {
+            Suite::Unstable => format!("Packages_{}", architecture),
+            _ => format!("Packages_{}_{}", suite, architecture),
         }
-        Ok(state)
     }
 
-    async fn download_packages(&self) -> Result<CacheState> {
-        let mut state = CacheState::NoUpdate;
-        for architecture in RELEASE_ARCHITECTURES {
-            let url = format!(
-                "https://deb.debian.org/debian/dists/unstable/main/binary-{}/Packages.xz",
-                architecture
+    fn packages_downloads(&self, suite: Suite) -> Result<Vec<Download>> {
+        let release = self.release(suite)?;
+        let mut downloads = vec![];
+        for architecture in release.binary_architectures() {
+            let file = format!("main/binary-{}/Packages.xz", architecture);
+            downloads.push(
+                Download::new(
+                    self.fetcher.archive_url(suite, &file),
+                    Self::packages_name(suite, &architecture),
+                )
+                .with_checksum(Self::expected_checksum(&release, &file)?),
             );
-            let dest = format!("Packages_{}", architecture);
-            if self
-                .downloader
-                .download_file(&url, self.get_cache_path(&dest)?)
-                .await?
-                == CacheState::FreshFiles
-            {
-                state = CacheState::FreshFiles;
-            }
         }
-        Ok(state)
+        Ok(downloads)
     }
 
-    async fn download_ftbfs_bugs(&self, codename: Codename) -> Result<CacheState> {
-        let url = format!("https://udd.debian.org/bugs/?release={}&ftbfs=only&merged=ign&done=ign&rc=1&sortby=id&sorto=asc&format=yaml", codename);
-        let dest = format!("udd-ftbfs-bugs-{}.yaml", codename);
-        self.downloader
-            .download_file(&url, self.get_cache_path(dest)?)
-            .await
+    fn expected_checksum<'a>(release: &'a Release, file: &str) -> Result<&'a Checksum> {
+        release
+            .sha256_for(file)
+            .ok_or_else(|| anyhow!("{} is not listed in the release file", file))
     }
 
-    async fn download_auto_removals(&self) -> Result<CacheState> {
-        self.downloader
-            .download_file(
-                "https://udd.debian.org/cgi-bin/autoremovals.yaml.cgi",
-                self.get_cache_path("autoremovals.yaml")?,
-            )
-            .await
+    fn ftbfs_bugs_query(&self, suite: &SuiteOrCodename) -> Result<UDDBugsQuery> {
+        let codename = self.resolve_codename(suite)?;
+        let mut query = UDDBugsQuery::new();
+        query
+            .with_release(&codename)
+            .with_rc()
+            .with_tag("ftbfs", Filter::Only)?;
+        Ok(query)
     }
 
-    async fn download_outdated_builtusing(&self) -> Result<CacheState> {
-        self.downloader
-            .download_file(
-                "https://ftp-master.debian.org/users/ansgar/outdated-built-using.txt",
-                self.get_cache_path("outdated-built-using.txt")?,
-            )
-            .await
+    fn udd_bugs_download(query: &UDDBugsQuery) -> Download {
+        Download::new(query.url(), Self::udd_bugs_name(query))
     }
 
     /*
-    async fn download_sources(&self) -> Result<CacheState> {
-        Ok(self
-            .downloader
-            .download_file(
-                "https://deb.debian.org/debian/dists/unstable/main/source/Sources.xz",
-                self.get_cache_path("Sources")?,
-            )
-            .await?)
+    fn sources_download(&self) -> Download {
+        Download::new(
+            self.fetcher.archive_url(Suite::Unstable, "main/source/Sources.xz"),
+            "Sources",
+        )
     }
     */
 
+    // Suites whose release files are required to download the given entry
+    fn required_releases(entry: &CacheEntries) -> Vec<Suite> {
+        match entry {
+            // codenames are resolved with the release files
+            CacheEntries::Releases | CacheEntries::FTBFSBugs(_) => RELEASE_SUITES.to_vec(),
+            CacheEntries::Packages => vec![Suite::Unstable],
+            CacheEntries::SuitePackages(suite)
+            | CacheEntries::Contents(suite)
+            | CacheEntries::ArchitectureContents(suite, _) => vec![*suite],
+            _ => vec![],
+        }
+    }
+
+    fn downloads(&self, entry: &CacheEntries) -> Result<Vec<Download>> {
+        Ok(match entry {
+            CacheEntries::Excuses => vec![Download::new(EXCUSES_URL, "excuses.yaml")],
+            CacheEntries::Packages => self.packages_downloads(Suite::Unstable)?,
+            CacheEntries::SuitePackages(suite) => self.packages_downloads(*suite)?,
+            // CacheEntries::Sources => vec![self.sources_download()],
+            // release files are downloaded first
+            CacheEntries::Releases => vec![],
+            CacheEntries::FTBFSBugs(suite) => {
+                vec![Self::udd_bugs_download(&self.ftbfs_bugs_query(suite)?)]
+            }
+            CacheEntries::AutoRemovals => {
+                vec![Download::new(AUTOREMOVALS_URL, "autoremovals.yaml")]
+            }
+            CacheEntries::OutdatedBuiltUsing => vec![Download::new(
+                "https://ftp-master.debian.org/users/ansgar/outdated-built-using.txt",
+                "outdated-built-using.txt",
+            )],
+            CacheEntries::UDDBugs(query) => vec![Self::udd_bugs_download(query)],
+            CacheEntries::Contents(suite) => self.contents_downloads(*suite)?,
+            CacheEntries::ArchitectureContents(suite, architecture) => {
+                vec![self.contents_download(&*self.release(*suite)?, *suite, architecture)?]
+            }
+        })
+    }
+
+    /// Download the given entries concurrently
+    ///
+    /// The release files are downloaded first, as the other downloads depend on them for
+    /// checksums, architectures and codenames.
     pub async fn download(&self, entries: &[CacheEntries]) -> Result<CacheState> {
-        let mut state = CacheState::NoUpdate;
+        self.download_entries(entries, false).await
+    }
+
+    /// Download the given entries even if the cached files are up to date
+    pub async fn refresh(&self, entries: &[CacheEntries]) -> Result<CacheState> {
+        self.download_entries(entries, true).await
+    }
+
+    async fn download_entries(&self, entries: &[CacheEntries], force: bool) -> Result<CacheState> {
+        let mut suites: Vec<Suite> = vec![];
+        for suite in entries.iter().flat_map(Self::required_releases) {
+            if !suites.contains(&suite) {
+                suites.push(suite);
+            }
+        }
+        let releases: Vec<_> = suites
+            .into_iter()
+            .map(|suite| self.release_download(suite))
+            .collect();
+        let release_state = self.download_all(&releases, force).await?;
+        if release_state == CacheState::FreshFiles {
+            self.releases.lock().unwrap().clear();
+            self.suite_mapping.lock().unwrap().take();
+        }
+
+        let mut downloads = vec![];
         for entry in entries {
-            let new_state = match entry {
-                CacheEntries::Excuses => self.download_excuses().await?,
-                CacheEntries::Packages => self.download_packages().await?,
-                // CacheEntries::Sources => self.download_sources().await?,
-                CacheEntries::FTBFSBugs(codename) => self.download_ftbfs_bugs(*codename).await?,
-                CacheEntries::AutoRemovals => self.download_auto_removals().await?,
-                CacheEntries::OutdatedBuiltUsing => self.download_outdated_builtusing().await?,
-                CacheEntries::Contents(suite) => self.download_contents(*suite).await?,
-            };
-            if new_state == CacheState::FreshFiles {
+            downloads.extend(self.downloads(entry)?);
+        }
+        let state = self.download_all(&downloads, force).await?;
+
+        if release_state == CacheState::FreshFiles {
+            Ok(CacheState::FreshFiles)
+        } else {
+            Ok(state)
+        }
+    }
+
+    // Download a file into the cache and drop the pre-parsed data of outdated files
+    async fn download_file(
+        &self,
+        download: &Download,
+        force: bool,
+        pb: &ProgressBar,
+    ) -> Result<CacheState> {
+        let state = self
+            .fetcher
+            .download(download, force, &DownloadProgress(pb))
+            .await
+            .with_context(|| format!("Failed to download '{}'", download.url))?;
+        match state {
+            State::Downloaded => {
+                ParsedIndex::remove_all(&self.fetcher.store().path(&download.name))?;
+                debug!("Download of {} to {} done", download.url, download.name);
+                Ok(CacheState::FreshFiles)
+            }
+            State::NotModified => {
+                debug!("Skipping {}: the file was not modified", download.url);
+                Ok(CacheState::NoUpdate)
+            }
+        }
+    }
+
+    /// Download all files concurrently
+    ///
+    /// At most `parallel_downloads` downloads run at the same time. All downloads are attempted
+    /// even if some of them fail; the first error is reported. If `force` is set, local copies
+    /// are never reused.
+    async fn download_all(&self, downloads: &[Download], force: bool) -> Result<CacheState> {
+        if downloads.is_empty() {
+            return Ok(CacheState::NoUpdate);
+        }
+
+        let mp = MultiProgress::new();
+        let pbs: Vec<ProgressBar> = downloads
+            .iter()
+            .map(|download| {
+                let pb = mp.add(ProgressBar::new(0));
+                pb.set_style(default_progress_style()
+                .template("{msg}: {spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")
+                );
+                pb.set_message(format!("Waiting for {}", download.url));
+                pb
+            })
+            .collect();
+        // the progress bars are only drawn while the multi progress bar is joined
+        let drawer = tokio::task::spawn_blocking(move || mp.join());
+
+        let results: Vec<Result<CacheState>> = stream::iter(downloads.iter().zip(pbs.iter()))
+            .map(|(download, pb)| async move {
+                let result = self.download_file(download, force, pb).await;
+                match &result {
+                    Ok(CacheState::FreshFiles) => {
+                        pb.finish_with_message(format!("Downloaded {}", download.url))
+                    }
+                    Ok(CacheState::NoUpdate) => pb.finish_and_clear(),
+                    Err(_) => {
+                        pb.abandon_with_message(format!("Failed to download {}", download.url))
+                    }
+                }
+                result
+            })
+            .buffer_unordered(self.parallel_downloads)
+            .collect()
+            .await;
+        drop(pbs);
+        drawer.await??;
+
+        let mut state = CacheState::NoUpdate;
+        for result in results {
+            if result? == CacheState::FreshFiles {
                 state = CacheState::FreshFiles;
             }
         }
         Ok(state)
     }
 
+    /// Return the cache directory
+    pub fn cache_directory(&self) -> PathBuf {
+        self.base_directory.get_cache_home()
+    }
+
+    /// Return all cached files, i.e., without metadata and temporary files
+    pub fn cached_files(&self) -> Result<Vec<PathBuf>> {
+        let directory = self.cache_directory();
+        if !directory.exists() {
+            return Ok(vec![]);
+        }
+
+        let mut files = vec![];
+        for entry in fs::read_dir(directory)? {
+            let path = entry?.path();
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            if path.is_file()
+                && !name.ends_with(METADATA_SUFFIX)
+                && !name.ends_with(PARSED_SUFFIX)
+                && !name.starts_with('.')
+            {
+                files.push(path);
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+
+    /// Remove a cached file, its metadata and its pre-parsed data
+    pub fn remove_cached_file<P>(&self, path: P) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        Metadata::remove(&path)?;
+        ParsedIndex::remove_all(path.as_ref())?;
+        Ok(fs::remove_file(path)?)
+    }
+
     pub fn get_cache_path<P>(&self, path: P) -> Result<PathBuf>
     where
         P: AsRef<Path>,
@@ -301,25 +679,218 @@ impl Cache {
         )?))
     }
 
+    // Load the archive keyring on first use
+    fn archive_keyring(&self) -> Result<&Keyring> {
+        if let Some(keyring) = self.archive_keyring.get() {
+            return Ok(keyring);
+        }
+        let mut keyring = Keyring::default();
+        keyring.add_keyring(ARCHIVE_KEYRING, false)?;
+        Ok(self.archive_keyring.get_or_init(|| keyring))
+    }
+
+    /// Load the cached release file of a suite
+    ///
+    /// If requested, the signature of the release file is verified against the archive keyring.
+    /// The release file is only loaded once.
+    pub fn release(&self, suite: Suite) -> Result<Arc<Release>> {
+        if let Some(release) = self.releases.lock().unwrap().get(&suite) {
+            return Ok(Arc::clone(release));
+        }
+        let release = Arc::new(self.load_release(suite)?);
+        self.releases
+            .lock()
+            .unwrap()
+            .insert(suite, Arc::clone(&release));
+        Ok(release)
+    }
+
+    fn load_release(&self, suite: Suite) -> Result<Release> {
+        let path = self.get_cache_path(format!("InRelease_{}", suite))?;
+        let data = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read '{}'", path.display()))?;
+        let text = if self.verify_release {
+            // only the verified text is parsed
+            match self.archive_keyring()?.verify(&data) {
+                SignedText {
+                    signature: Signature::Valid(signer),
+                    text,
+                } => {
+                    debug!("{}: signed by {}", path.display(), signer);
+                    text
+                }
+                SignedText { signature, .. } => {
+                    return Err(anyhow!("Refusing to use {}: {}", path.display(), signature))
+                }
+            }
+        } else {
+            data
+        };
+        release::from_signed_str(&text)
+            .with_context(|| format!("Failed to parse '{}'", path.display()))
+    }
+
+    /// Load the mapping between suites and codenames from the cached release files
+    ///
+    /// Release files that are missing or cannot be used are skipped. Codenames of the
+    /// corresponding suites are unknown, except for unstable and experimental. If no release
+    /// files are cached at all, the built-in mapping is used. The mapping is only loaded once.
+    pub fn suite_mapping(&self) -> Result<Arc<SuiteMapping>> {
+        if let Some(mapping) = self.suite_mapping.lock().unwrap().as_ref() {
+            return Ok(Arc::clone(mapping));
+        }
+        let mapping = Arc::new(self.load_suite_mapping()?);
+        *self.suite_mapping.lock().unwrap() = Some(Arc::clone(&mapping));
+        Ok(mapping)
+    }
+
+    fn load_suite_mapping(&self) -> Result<SuiteMapping> {
+        let mut mapping = SuiteMapping::default();
+        for suite in RELEASE_SUITES {
+            if !self
+                .get_cache_path(format!("InRelease_{}", suite))?
+                .exists()
+            {
+                continue;
+            }
+            match self.release(suite) {
+                Ok(release) => mapping.add_release(&release),
+                Err(e) => warn!("{:#}", e),
+            }
+        }
+        Ok(mapping)
+    }
+
+    /// Resolve a suite or codename to a suite
+    ///
+    /// Codenames are resolved with the mapping from the cached release files.
+    pub fn resolve_suite(&self, suite: &SuiteOrCodename) -> Result<Suite> {
+        self.suite_mapping()?
+            .resolve_suite(suite)
+            .ok_or_else(|| anyhow!("unknown codename: {}", suite))
+    }
+
+    /// Resolve a suite or codename to a codename
+    ///
+    /// Suites are resolved with the mapping from the cached release files.
+    pub fn resolve_codename(&self, suite: &SuiteOrCodename) -> Result<String> {
+        self.suite_mapping()?
+            .resolve_codename(suite)
+            .ok_or_else(|| anyhow!("unknown codename of {}", suite))
+    }
+
+    // Name of the cached results of a UDD bugs query
+    fn udd_bugs_name(query: &UDDBugsQuery) -> String {
+        format!("udd-bugs-{}.yaml", query.key())
+    }
+
+    /// Return the path of the cached results of a UDD bugs query
+    ///
+    /// Each distinct query is cached under its own key.
+    pub fn get_udd_bugs_path(&self, query: &UDDBugsQuery) -> Result<PathBuf> {
+        self.get_cache_path(Self::udd_bugs_name(query))
+    }
+
+    pub fn get_ftbfs_bugs_path(&self, suite: &SuiteOrCodename) -> Result<PathBuf> {
+        self.get_udd_bugs_path(&self.ftbfs_bugs_query(suite)?)
+    }
+
+    /// Return the architectures of a suite
+    ///
+    /// The architectures are taken from the cached release file of the suite, which needs to be
+    /// downloaded first.
+    pub fn architectures(&self, suite: Suite) -> Result<Vec<Architecture>> {
+        self.release(suite)
+            .map(|release| release.binary_architectures())
+            .with_context(|| format!("Unknown architectures for {}", suite))
+    }
+
     pub fn get_package_paths(&self) -> Result<Vec<PathBuf>> {
+        Ok(self
+            .get_suite_package_paths(Suite::Unstable)?
+            .into_iter()
+            .map(|(_, path)| path)
+            .collect())
+    }
+
+    pub fn get_suite_package_paths(&self, suite: Suite) -> Result<Vec<(Architecture, PathBuf)>> {
         let mut all_paths = vec![];
-        for architecture in RELEASE_ARCHITECTURES {
-            all_paths.push(self.get_cache_path(format!("Packages_{}", architecture))?);
+        for architecture in self.architectures(suite)? {
+            let path = self.get_cache_path(Self::packages_name(suite, &architecture))?;
+            all_paths.push((architecture, path));
         }
         Ok(all_paths)
     }
 
-    pub fn get_content_paths(&self, suite: Suite) -> Result<Vec<(Architecture, PathBuf)>> {
-        let mut all_paths = vec![];
-        for architecture in RELEASE_ARCHITECTURES
-            .into_iter()
-            .chain([Architecture::All].into_iter())
+    /// Return the path of the Contents file of a suite on an architecture
+    ///
+    /// The architecture needs to be available in the suite or be all.
+    pub fn get_content_path(&self, suite: Suite, architecture: &Architecture) -> Result<PathBuf> {
+        if *architecture != Architecture::All && !self.architectures(suite)?.contains(architecture)
         {
-            all_paths.push((
-                architecture,
-                self.get_cache_path(format!("Contents_{}_{}", suite, architecture))?,
+            return Err(anyhow!(
+                "no Contents file for {} on {}",
+                suite,
+                architecture
             ));
         }
-        Ok(all_paths)
+        self.get_cache_path(Self::contents_name(suite, architecture))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        collections::{HashMap, HashSet},
+        fs,
+    };
+
+    use assorted_debian_utils::{fetch::Metadata, version::PackageVersion};
+
+    use super::ParsedIndex;
+
+    #[test]
+    fn parsed_index() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("Packages_amd64");
+        fs::write(&path, "Package: foo\n").unwrap();
+
+        let mut data = HashMap::<String, HashSet<PackageVersion>>::new();
+        data.entry("foo".into())
+            .or_default()
+            .insert(PackageVersion::try_from("1.0-1").unwrap());
+
+        // without checksum nothing is stored
+        ParsedIndex::store(&path, "test", &data).unwrap();
+        assert_eq!(
+            ParsedIndex::load::<HashMap<String, HashSet<PackageVersion>>>(&path, "test"),
+            None
+        );
+
+        let mut metadata = Metadata {
+            url: "https://deb.debian.org/debian/dists/unstable/main/binary-amd64/Packages.xz"
+                .into(),
+            sha256: Some("1234".into()),
+            ..Default::default()
+        };
+        metadata.store(&path).unwrap();
+        ParsedIndex::store(&path, "test", &data).unwrap();
+        assert_eq!(ParsedIndex::load(&path, "test"), Some(data.clone()));
+        assert_eq!(
+            ParsedIndex::load::<HashMap<String, HashSet<PackageVersion>>>(&path, "other"),
+            None
+        );
+
+        // a new download invalidates the pre-parsed data
+        metadata.sha256 = Some("5678".into());
+        metadata.store(&path).unwrap();
+        assert_eq!(
+            ParsedIndex::load::<HashMap<String, HashSet<PackageVersion>>>(&path, "test"),
+            None
+        );
+
+        ParsedIndex::remove_all(&path).unwrap();
+        assert!(!ParsedIndex::path(&path, "test").exists());
+        assert!(path.exists());
     }
 }
@@ -0,0 +1,268 @@
+// Copyright 2022 Sebastian Ramacher
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::collections::HashSet;
+
+use anyhow::Result;
+use assorted_debian_utils::{
+    architectures::Architecture,
+    archive::{Suite, SuiteOrCodename},
+    contents,
+    contents_diff::{self, aliased_path, Change, ContentsMap, Partition},
+};
+use clap::Parser;
+use log::{debug, warn};
+use rayon::prelude::*;
+
+use crate::{
+    config::{self, CacheEntries},
+    BaseOptions,
+};
+
+#[derive(Debug, Parser)]
+pub(crate) struct ContentsDiffOptions {
+    /// Suite or codename to compare from
+    #[clap(long, default_value = "stable")]
+    from: SuiteOrCodename,
+    /// Suite or codename to compare to
+    #[clap(long, default_value = "testing")]
+    to: SuiteOrCodename,
+    /// Only compare these architectures
+    ///
+    /// The files of all are merged into every architecture. Changes of files in all are reported
+    /// once for all, and only if all is selected.
+    #[clap(short, long)]
+    architecture: Option<Vec<Architecture>>,
+    /// Only report files below this path prefix
+    ///
+    /// Files moved between aliased directories are reported if either path matches.
+    #[clap(long = "path")]
+    paths: Vec<String>,
+    /// Only report changes involving this binary package
+    #[clap(long = "package")]
+    packages: Vec<String>,
+}
+
+pub(crate) struct ContentsDiff {
+    cache: config::Cache,
+    options: ContentsDiffOptions,
+    old_suite: Suite,
+    new_suite: Suite,
+    prefixes: Vec<String>,
+}
+
+impl ContentsDiff {
+    pub(crate) fn new(base_options: BaseOptions, options: ContentsDiffOptions) -> Result<Self> {
+        let cache = config::Cache::new(&base_options)?;
+        Self::download_releases(&cache)?;
+        let old_suite = cache.resolve_suite(&options.from)?;
+        let new_suite = cache.resolve_suite(&options.to)?;
+        // paths in Contents files have no leading slash
+        let prefixes = options
+            .paths
+            .iter()
+            .map(|path| path.trim_start_matches('/').to_owned())
+            .collect();
+
+        Ok(Self {
+            cache,
+            options,
+            old_suite,
+            new_suite,
+            prefixes,
+        })
+    }
+
+    #[tokio::main]
+    async fn download_releases(cache: &config::Cache) -> Result<()> {
+        cache.download(&[CacheEntries::Releases]).await?;
+        Ok(())
+    }
+
+    #[tokio::main]
+    async fn download_to_cache(&self, architectures: &[Architecture]) -> Result<()> {
+        // only the Contents files of the compared architectures are downloaded
+        let mut entries = vec![];
+        for suite in [self.old_suite, self.new_suite] {
+            entries.extend(
+                architectures
+                    .iter()
+                    .chain([&Architecture::All])
+                    .map(|architecture| {
+                        CacheEntries::ArchitectureContents(suite, architecture.clone())
+                    }),
+            );
+        }
+        self.cache.download(&entries).await?;
+        Ok(())
+    }
+
+    fn is_selected_architecture(&self, architecture: &Architecture) -> bool {
+        match &self.options.architecture {
+            Some(selected) => selected.contains(architecture),
+            None => true,
+        }
+    }
+
+    fn matches_prefix(&self, path: &str) -> bool {
+        self.prefixes.is_empty() || self.prefixes.iter().any(|prefix| path.starts_with(prefix))
+    }
+
+    /// Check if a path or its aliased path matches one of the path prefixes
+    fn is_selected_path(&self, path: &str) -> bool {
+        self.matches_prefix(path)
+            || aliased_path(path).is_some_and(|alias| self.matches_prefix(&alias))
+    }
+
+    fn is_selected_change(&self, change: &Change) -> bool {
+        self.options.packages.is_empty()
+            || change
+                .packages()
+                .any(|package| self.options.packages.contains(package))
+    }
+
+    fn load_contents(
+        &self,
+        suite: Suite,
+        architecture: &Architecture,
+        partition: Partition,
+    ) -> Result<ContentsMap> {
+        let path = self.cache.get_content_path(suite, architecture)?;
+        debug!(
+            "Processing contents for {} on {} (partition {}): {:?}",
+            suite, architecture, partition, path
+        );
+
+        let mut contents = ContentsMap::new();
+        for entry in contents::from_file(path)? {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    warn!("Unable to process entry: {}", err);
+                    continue;
+                }
+            };
+            if !partition.contains(&entry.path) || !self.is_selected_path(&entry.path) {
+                continue;
+            }
+
+            contents
+                .entry(entry.path)
+                .or_default()
+                .extend(entry.packages.into_iter().map(|package| package.package));
+        }
+        Ok(contents)
+    }
+
+    /// Load the files of a suite on `architecture` with the files of all merged in
+    fn load_merged_contents(
+        &self,
+        suite: Suite,
+        architecture: &Architecture,
+        all_contents: &ContentsMap,
+        partition: Partition,
+    ) -> Result<ContentsMap> {
+        let mut contents = self.load_contents(suite, architecture, partition)?;
+        for (path, packages) in all_contents {
+            contents
+                .entry(path.clone())
+                .or_default()
+                .extend(packages.iter().cloned());
+        }
+        Ok(contents)
+    }
+
+    /// Compare the files of an architecture in one partition
+    ///
+    /// Changes that are found by comparing all with all are not reported again.
+    fn diff_architecture(
+        &self,
+        architecture: &Architecture,
+        all_contents: (&ContentsMap, &ContentsMap),
+        all_changes: &HashSet<&Change>,
+        partition: Partition,
+    ) -> Result<Vec<Change>> {
+        let old =
+            self.load_merged_contents(self.old_suite, architecture, all_contents.0, partition)?;
+        let new =
+            self.load_merged_contents(self.new_suite, architecture, all_contents.1, partition)?;
+        Ok(contents_diff::diff(&old, &new)
+            .into_iter()
+            .filter(|change| !all_changes.contains(change) && self.is_selected_change(change))
+            .collect())
+    }
+
+    pub(crate) fn run(self) -> Result<()> {
+        // only architectures available in both suites can be compared
+        let new_architectures = self.cache.architectures(self.new_suite)?;
+        let mut architectures = vec![];
+        for architecture in self.cache.architectures(self.old_suite)? {
+            if !self.is_selected_architecture(&architecture) {
+                continue;
+            }
+            if new_architectures.contains(&architecture) {
+                architectures.push(architecture);
+            } else {
+                warn!(
+                    "Skipping {}: not available in {}",
+                    architecture, self.new_suite
+                );
+            }
+        }
+
+        self.download_to_cache(&architectures)?;
+
+        // the files of all are merged into every architecture, so only one partition of them is
+        // kept in memory at a time
+        let mut paths = vec![];
+        for suite in [self.old_suite, self.new_suite] {
+            paths.push(self.cache.get_content_path(suite, &Architecture::All)?);
+            for architecture in &architectures {
+                paths.push(self.cache.get_content_path(suite, architecture)?);
+                paths.push(self.cache.get_content_path(suite, &Architecture::All)?);
+            }
+        }
+        let mut all_changes = vec![];
+        let mut changes: Vec<Vec<Change>> = vec![vec![]; architectures.len()];
+        for partition in config::contents_partitions(&paths)? {
+            let old_all = self.load_contents(self.old_suite, &Architecture::All, partition)?;
+            let new_all = self.load_contents(self.new_suite, &Architecture::All, partition)?;
+            let partition_all_changes = contents_diff::diff(&old_all, &new_all);
+            let all_changes_set: HashSet<&Change> = partition_all_changes.iter().collect();
+
+            // architectures are processed in parallel, but the output is kept in order
+            let results: Vec<Result<Vec<Change>>> = architectures
+                .par_iter()
+                .map(|architecture| {
+                    self.diff_architecture(
+                        architecture,
+                        (&old_all, &new_all),
+                        &all_changes_set,
+                        partition,
+                    )
+                })
+                .collect();
+            for (changes, result) in changes.iter_mut().zip(results) {
+                changes.extend(result?);
+            }
+            all_changes.extend(
+                partition_all_changes
+                    .into_iter()
+                    .filter(|change| self.is_selected_change(change)),
+            );
+        }
+
+        if self.is_selected_architecture(&Architecture::All) {
+            architectures.push(Architecture::All);
+            changes.push(all_changes);
+        }
+        for (architecture, mut changes) in architectures.iter().zip(changes) {
+            changes.sort_by(|lhs, rhs| lhs.path().cmp(rhs.path()));
+            for change in changes {
+                println!("{}: {}", architecture, change);
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,215 @@
+// Copyright 2022 Sebastian Ramacher
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use anyhow::Result;
+use assorted_debian_utils::{
+    architectures::Architecture,
+    archive::{Suite, SuiteOrCodename},
+    contents::{self, ContentsEntry},
+};
+use clap::Parser;
+use log::{debug, warn};
+use rayon::prelude::*;
+use regex::Regex;
+
+use crate::{
+    config::{self, CacheEntries},
+    BaseOptions,
+};
+
+#[derive(Debug, Parser)]
+pub(crate) struct ContentsSearchOptions {
+    /// Suites or codenames to search
+    #[clap(short, long, default_value = "unstable")]
+    suite: Vec<SuiteOrCodename>,
+    /// Architectures to search (all is always searched)
+    #[clap(short, long, default_value = "amd64")]
+    architecture: Vec<Architecture>,
+    /// List the files of a package instead of searching for a path
+    #[clap(short, long)]
+    list: bool,
+    /// Path or glob (with `*` and `?`) to search for, or package to list
+    query: String,
+}
+
+/// What to look for in the Contents files
+#[derive(Debug)]
+enum Query {
+    /// Files with exactly this path
+    Path(String),
+    /// Files matching a glob
+    Glob(Regex),
+    /// Files of a package
+    Package(String),
+}
+
+impl Query {
+    fn new(query: &str, list: bool) -> Result<Self> {
+        if list {
+            return Ok(Query::Package(query.to_owned()));
+        }
+
+        // paths in Contents files have no leading slash
+        let query = query.trim_start_matches('/');
+        if !query.contains(['*', '?']) {
+            return Ok(Query::Path(query.to_owned()));
+        }
+        Ok(Query::Glob(glob_to_regex(query)?))
+    }
+
+    fn matches(&self, entry: &ContentsEntry) -> bool {
+        match self {
+            Query::Path(path) => entry.path == *path,
+            Query::Glob(regex) => regex.is_match(&entry.path),
+            Query::Package(package) => entry.package_names().any(|name| name == package),
+        }
+    }
+}
+
+/// Convert a glob to a regular expression matching the whole path
+fn glob_to_regex(glob: &str) -> Result<Regex> {
+    let mut pattern = String::from("^");
+    for c in glob.chars() {
+        match c {
+            '*' => pattern.push_str(".*"),
+            '?' => pattern.push('.'),
+            _ => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    pattern.push('$');
+    Ok(Regex::new(&pattern)?)
+}
+
+pub(crate) struct ContentsSearch {
+    cache: config::Cache,
+    options: ContentsSearchOptions,
+    suites: Vec<Suite>,
+    query: Query,
+}
+
+impl ContentsSearch {
+    pub(crate) fn new(base_options: BaseOptions, options: ContentsSearchOptions) -> Result<Self> {
+        let cache = config::Cache::new(&base_options)?;
+        Self::download_releases(&cache)?;
+        let suites = options
+            .suite
+            .iter()
+            .map(|suite| cache.resolve_suite(suite))
+            .collect::<Result<_>>()?;
+        let query = Query::new(&options.query, options.list)?;
+
+        Ok(Self {
+            cache,
+            options,
+            suites,
+            query,
+        })
+    }
+
+    #[tokio::main]
+    async fn download_releases(cache: &config::Cache) -> Result<()> {
+        cache.download(&[CacheEntries::Releases]).await?;
+        Ok(())
+    }
+
+    #[tokio::main]
+    async fn download_to_cache(&self, searches: &[(Suite, Architecture)]) -> Result<()> {
+        // only the Contents files that are searched are downloaded
+        let entries: Vec<_> = searches
+            .iter()
+            .map(|(suite, architecture)| {
+                CacheEntries::ArchitectureContents(*suite, architecture.clone())
+            })
+            .collect();
+        self.cache.download(&entries).await?;
+        Ok(())
+    }
+
+    /// Search the Contents file of `suite` on `architecture`
+    fn search(&self, suite: Suite, architecture: &Architecture) -> Result<Vec<ContentsEntry>> {
+        let path = self.cache.get_content_path(suite, architecture)?;
+        debug!(
+            "Searching contents for {} on {}: {:?}",
+            suite, architecture, path
+        );
+
+        let mut matches = Vec::new();
+        for entry in contents::from_file(path)? {
+            match entry {
+                Ok(entry) => {
+                    if self.query.matches(&entry) {
+                        matches.push(entry);
+                    }
+                }
+                Err(err) => warn!("Unable to process entry: {}", err),
+            }
+        }
+        Ok(matches)
+    }
+
+    pub(crate) fn run(self) -> Result<()> {
+        let mut searches = vec![];
+        for suite in &self.suites {
+            let architectures = self.cache.architectures(*suite)?;
+            for architecture in &self.options.architecture {
+                if *architecture == Architecture::All {
+                    continue;
+                }
+                if architectures.contains(architecture) {
+                    searches.push((*suite, architecture.clone()));
+                } else {
+                    warn!("Skipping {}: not available in {}", architecture, suite);
+                }
+            }
+            searches.push((*suite, Architecture::All));
+        }
+        self.download_to_cache(&searches)?;
+
+        // the Contents files are searched in parallel, but the output is kept in order
+        let results: Vec<Result<Vec<ContentsEntry>>> = searches
+            .par_iter()
+            .map(|(suite, architecture)| self.search(*suite, architecture))
+            .collect();
+        for ((suite, architecture), result) in searches.iter().zip(results) {
+            for entry in result? {
+                if self.options.list {
+                    println!("{} {}: /{}", suite, architecture, entry.path);
+                } else {
+                    for package in entry.package_names() {
+                        println!("{} {}: {}: /{}", suite, architecture, package, entry.path);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use assorted_debian_utils::contents::ContentsEntry;
+
+    use super::Query;
+
+    #[test]
+    fn query() {
+        let entry =
+            ContentsEntry::try_from("usr/lib/x86_64-linux-gnu/libfoo.so.1 libs/libfoo1").unwrap();
+
+        assert!(Query::new("/usr/lib/x86_64-linux-gnu/libfoo.so.1", false)
+            .unwrap()
+            .matches(&entry));
+        assert!(!Query::new("usr/lib/x86_64-linux-gnu/libfoo.so", false)
+            .unwrap()
+            .matches(&entry));
+        assert!(Query::new("/usr/lib/*/libfoo.so.?", false)
+            .unwrap()
+            .matches(&entry));
+        assert!(!Query::new("/usr/lib/*/libbar.so*", false)
+            .unwrap()
+            .matches(&entry));
+        assert!(Query::new("libfoo1", true).unwrap().matches(&entry));
+        assert!(!Query::new("libfoo", true).unwrap().matches(&entry));
+    }
+}
@@ -7,16 +7,28 @@ use clap::{Parser, Subcommand};
 use log::trace;
 
 mod binnmu_buildinfo;
+mod bugs;
+mod built_against;
+mod built_using_report;
+mod cache_maintenance;
 pub(crate) mod config;
+mod contents_diff;
+mod contents_search;
 mod grep_excuses;
 mod nmu_eso;
 mod prepare_binnmus;
 mod process_excuses;
+mod signature;
 pub(crate) mod source_packages;
-pub(crate) mod udd_bugs;
 mod usrmerged;
 
 use binnmu_buildinfo::{BinNMUBuildinfo, BinNMUBuildinfoOptions};
+use bugs::{Bugs, BugsOptions};
+use built_against::{BuiltAgainst, BuiltAgainstOptions};
+use built_using_report::{BuiltUsingReport, BuiltUsingReportOptions};
+use cache_maintenance::{CacheMaintenance, CacheMaintenanceOptions};
+use contents_diff::{ContentsDiff, ContentsDiffOptions};
+use contents_search::{ContentsSearch, ContentsSearchOptions};
 use grep_excuses::{GrepExcuses, GrepExcusesOptions};
 use nmu_eso::{NMUOutdatedBuiltUsing, NMUOutdatedBuiltUsingOptions};
 use prepare_binnmus::{PrepareBinNMUs, PrepareBinNMUsOptions};
@@ -28,6 +40,15 @@ pub(crate) struct BaseOptions {
     /// Force download of files
     #[clap(long)]
     force_download: bool,
+    /// Verify the signatures of the InRelease files against the Debian archive keyring
+    #[clap(long)]
+    verify_release: bool,
+    /// Maximal number of concurrent downloads
+    #[clap(long, default_value = "4")]
+    parallel_downloads: usize,
+    /// Number of jobs to process files in parallel (0 uses the number of CPUs)
+    #[clap(short, long, default_value = "0")]
+    jobs: usize,
     /// Force processing
     #[clap(short, long = "force")]
     force_processing: bool,
@@ -94,15 +115,43 @@ enum DrtToolsCommands {
     /// Prepare binNMUs based on a list of buildinfo files
     #[clap(name = "binNMU-buildinfo")]
     BinNMUBuildinfo(BinNMUBuildinfoOptions),
+    /// Prepare binNMUs for packages built against a specific version of a package
+    ///
+    /// This command searches a directory tree of buildinfo files, e.g., a local mirror of
+    /// buildinfos.debian.net, for builds that had a package with a matching version installed.
+    /// Builds of versions that are no longer in the archive are skipped.
+    #[clap(name = "binNMU-built-against")]
+    BuiltAgainst(BuiltAgainstOptions),
     /// Grep excuses
     #[clap(name = "grep-excuses")]
     GrepExcuses(GrepExcusesOptions),
     /// Prepare binNMUs to rebuild for outdated Built-Using
     #[clap(name = "nmu-eso")]
     NMUOutdatedBuiltUsing(NMUOutdatedBuiltUsingOptions),
+    /// Report packages with outdated Built-Using grouped by the embedded source package
+    #[clap(name = "built-using-report")]
+    BuiltUsingReport(BuiltUsingReportOptions),
     /// Check state of /usr-merged bugs
     #[clap(name = "usrmerged")]
     UsrMerged(UsrMergedOptions),
+    /// Compare the Contents indices of two suites
+    ///
+    /// Files that were added, removed, moved to other packages, or moved between directories
+    /// aliased by merged-/usr are reported.
+    #[clap(name = "contents-diff")]
+    ContentsDiff(ContentsDiffOptions),
+    /// Search the Contents indices for the packages shipping a path or list the files of a package
+    #[clap(name = "contents")]
+    ContentsSearch(ContentsSearchOptions),
+    /// List bugs from UDD
+    ///
+    /// For example, RC bugs of packages in a transition that only affect testing can be listed
+    /// with `bugs --rc --affects testing --not-affects unstable <sources>`.
+    #[clap(name = "bugs")]
+    Bugs(BugsOptions),
+    /// Inspect and maintain the cache
+    #[clap(name = "cache")]
+    Cache(CacheMaintenanceOptions),
 }
 
 fn main() -> Result<()> {
@@ -115,6 +164,10 @@ fn main() -> Result<()> {
     trace!("base options {:?}", opts.base_options);
     trace!("command: {:?}", opts.command);
 
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(opts.base_options.jobs)
+        .build_global()?;
+
     match opts.command {
         DrtToolsCommands::ProcessExcuses(pe_opts) => {
             let process_excuses = ProcessExcuses::new(opts.base_options, pe_opts)?;
@@ -128,6 +181,10 @@ fn main() -> Result<()> {
             let binnmus_buildinfo = BinNMUBuildinfo::new(opts.base_options, bb_opts)?;
             binnmus_buildinfo.run()
         }
+        DrtToolsCommands::BuiltAgainst(ba_opts) => {
+            let built_against = BuiltAgainst::new(opts.base_options, ba_opts)?;
+            built_against.run()
+        }
         DrtToolsCommands::GrepExcuses(ge_opts) => {
             let grep_excuses = GrepExcuses::new(opts.base_options, ge_opts)?;
             grep_excuses.run()
@@ -136,9 +193,29 @@ fn main() -> Result<()> {
             let nmu_eso = NMUOutdatedBuiltUsing::new(opts.base_options, eso_opts)?;
             nmu_eso.run()
         }
+        DrtToolsCommands::BuiltUsingReport(bur_opts) => {
+            let built_using_report = BuiltUsingReport::new(opts.base_options, bur_opts)?;
+            built_using_report.run()
+        }
         DrtToolsCommands::UsrMerged(um_opts) => {
             let usr_merged = UsrMerged::new(opts.base_options, um_opts)?;
             usr_merged.run()
         }
+        DrtToolsCommands::ContentsDiff(cd_opts) => {
+            let contents_diff = ContentsDiff::new(opts.base_options, cd_opts)?;
+            contents_diff.run()
+        }
+        DrtToolsCommands::ContentsSearch(cs_opts) => {
+            let contents_search = ContentsSearch::new(opts.base_options, cs_opts)?;
+            contents_search.run()
+        }
+        DrtToolsCommands::Bugs(bugs_opts) => {
+            let bugs = Bugs::new(opts.base_options, bugs_opts)?;
+            bugs.run()
+        }
+        DrtToolsCommands::Cache(cache_opts) => {
+            let cache_maintenance = CacheMaintenance::new(opts.base_options, cache_opts)?;
+            cache_maintenance.run()
+        }
     }
 }
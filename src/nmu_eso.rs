@@ -1,21 +1,25 @@
 // Copyright 2021-2022 Sebastian Ramacher
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use std::{collections::HashSet, io::BufRead, path::Path};
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{BufRead, BufReader},
+};
 
 use anyhow::Result;
 use assorted_debian_utils::{
     architectures::Architecture,
-    archive::{Codename, Suite},
+    archive::SuiteOrCodename,
+    outdated_built_using, stanzas,
+    udd_bugs::{self, UDDBugs},
     wb::{BinNMU, SourceSpecifier, WBCommandBuilder},
 };
-use indicatif::{ProgressBar, ProgressIterator};
-use log::{debug, trace};
+use log::{debug, trace, warn};
 use serde::Deserialize;
 
 use crate::{
     config::{self, CacheEntries, CacheState},
-    udd_bugs::{load_bugs_from_reader, UDDBugs},
     BaseOptions, BinNMUsOptions,
 };
 
@@ -43,101 +47,89 @@ impl NMUOutdatedBuiltUsing {
         options: NMUOutdatedBuiltUsingOptions,
     ) -> Result<Self> {
         Ok(Self {
-            cache: config::Cache::new(base_options.force_download)?,
+            cache: config::Cache::new(&base_options)?,
             base_options,
             options,
         })
     }
 
     #[tokio::main]
-    async fn download_to_cache(&self, codename: &Codename) -> Result<CacheState> {
+    async fn download_to_cache(&self, suite: &SuiteOrCodename) -> Result<CacheState> {
         self.cache
-            .download(&[CacheEntries::Packages, CacheEntries::FTBFSBugs(*codename)])
+            .download(&[
+                CacheEntries::Releases,
+                CacheEntries::Packages,
+                CacheEntries::FTBFSBugs(suite.clone()),
+            ])
             .await?;
         self.cache
             .download(&[CacheEntries::OutdatedBuiltUsing])
             .await
     }
 
-    fn load_bugs(&self, codename: &Codename) -> Result<UDDBugs> {
-        load_bugs_from_reader(
-            self.cache
-                .get_cache_bufreader(format!("udd-ftbfs-bugs-{}.yaml", codename))?,
-        )
+    fn load_bugs(&self, suite: &SuiteOrCodename) -> Result<UDDBugs> {
+        Ok(udd_bugs::from_reader(BufReader::new(File::open(
+            self.cache.get_ftbfs_bugs_path(suite)?,
+        )?))?)
     }
 
-    fn parse_packages<P>(path: P) -> Result<HashSet<String>>
-    where
-        P: AsRef<Path>,
-    {
-        // read Package file
-        let binary_packages: Vec<BinaryPackage> = rfc822_like::from_file(path.as_ref())?;
-        let pb = ProgressBar::new(binary_packages.len() as u64);
-        pb.set_style(config::default_progress_style().template(
-            "{msg}: {spinner:.green} [{wide_bar:.cyan/blue}] {pos}/{len} ({per_sec}, {eta})",
-        ));
-        pb.set_message(format!(
-            "Processing {}",
-            path.as_ref().file_name().unwrap().to_str().unwrap()
-        ));
+    fn parse_packages(reader: &mut dyn BufRead) -> Result<HashSet<String>> {
+        // read Package file one stanza at a time
         // collect all sources with arch dependendent binaries having Built-Using set
-        Ok(binary_packages
-            .into_iter()
-            .progress_with(pb)
-            .filter(|binary_package| {
-                binary_package.built_using.is_some()
-                    && binary_package.architecture != Architecture::All
-            })
-            .map(|binary_package| {
-                if let Some(source_package) = &binary_package.source {
-                    source_package.split_whitespace().next().unwrap().into()
-                } else {
-                    // no Source set, so Source == Package
-                    binary_package.package
-                }
-            })
-            .collect())
+        let mut sources = HashSet::new();
+        for binary_package in stanzas::from_reader::<_, BinaryPackage>(reader) {
+            let binary_package = binary_package?;
+            if binary_package.built_using.is_none()
+                || binary_package.architecture == Architecture::All
+            {
+                continue;
+            }
+            sources.insert(if let Some(source_package) = &binary_package.source {
+                source_package.split_whitespace().next().unwrap().into()
+            } else {
+                // no Source set, so Source == Package
+                binary_package.package
+            });
+        }
+        Ok(sources)
     }
 
-    fn load_eso(&self, suite: &Suite) -> Result<Vec<String>> {
-        let codename = (*suite).into();
-        if self.download_to_cache(&codename)? == CacheState::NoUpdate
+    fn load_eso(&self, suite: &SuiteOrCodename) -> Result<Vec<String>> {
+        if self.download_to_cache(suite)? == CacheState::NoUpdate
             && !self.base_options.force_processing
         {
             return Ok(Vec::new());
         }
 
-        let ftbfs_bugs = self.load_bugs(&codename)?;
+        let ftbfs_bugs = self.load_bugs(suite)?;
+        let suite = self.cache.resolve_suite(suite)?;
         let mut actionable_sources = HashSet::<String>::new();
-        for path in self.cache.get_package_paths()? {
-            let sources = Self::parse_packages(path);
-            actionable_sources.extend(sources?);
+        for sources in config::process_in_parallel(
+            &self.cache.get_package_paths()?,
+            "nmu-eso",
+            Self::parse_packages,
+        )? {
+            actionable_sources.extend(sources);
         }
 
         let mut result = HashSet::new();
-        let reader = self.cache.get_cache_bufreader("outdated-built-using.txt")?;
-
-        for line in reader.lines() {
-            if line.is_err() {
-                break;
-            }
-
-            let line = line.unwrap();
-            trace!("Processing line: {}", line);
-            let split: Vec<&str> = line.split(" | ").collect();
-            if split.len() != 5 {
-                continue;
-            }
-
-            // check if suite matches
-            match Suite::try_from(split[0].trim()) {
-                Ok(ref source_suite) if source_suite == suite => {}
-                _ => {
+        for entry in outdated_built_using::from_reader(
+            self.cache.get_cache_bufreader("outdated-built-using.txt")?,
+        ) {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    warn!("Unable to process entry: {}", err);
                     continue;
                 }
+            };
+            trace!("Processing entry: {:?}", entry);
+            // check if suite matches
+            if entry.suite != suite {
+                continue;
             }
 
-            let source = split[1].trim().to_owned();
+            let source = entry.source;
             // not-binNMUable as the Built-Using package is binary-independent
             if !actionable_sources.contains(&source) {
                 debug!("Skipping {}: not actionable", source);
@@ -148,8 +140,11 @@ impl NMUOutdatedBuiltUsing {
                 debug!("Skipping {}: either gcc or binuitls", source);
                 continue;
             }
-            // check if package FTBFS
-            if let Some(bugs) = ftbfs_bugs.bugs_for_source(&source) {
+            // check if package FTBFS in the suite
+            if let Some(bugs) = ftbfs_bugs
+                .bugs_for_source(&source, Some(suite))
+                .filter(|bugs| bugs.iter().any(|bug| bug.is_rc()))
+            {
                 println!("# Skipping {} due to FTBFS bugs ...", source);
                 for bug in bugs {
                     debug!(
@@ -160,7 +155,7 @@ impl NMUOutdatedBuiltUsing {
                 continue;
             }
 
-            result.insert(split[1].trim().to_owned());
+            result.insert(source);
         }
 
         let mut result: Vec<String> = result.into_iter().collect();
@@ -169,8 +164,7 @@ impl NMUOutdatedBuiltUsing {
     }
 
     pub(crate) fn run(self) -> Result<()> {
-        let suite = self.options.suite.into();
-        let eso_sources = self.load_eso(&suite)?;
+        let eso_sources = self.load_eso(&self.options.suite)?;
 
         for source in eso_sources {
             let mut source = SourceSpecifier::new(&source);
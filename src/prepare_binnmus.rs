@@ -7,14 +7,14 @@ use std::path::PathBuf;
 
 use anyhow::Result;
 use assorted_debian_utils::{
-    archive::Codename,
+    archive::SuiteOrCodename,
+    udd_bugs::{self, UDDBugs},
     wb::{BinNMU, SourceSpecifier, WBCommandBuilder},
 };
 use clap::Parser;
 
 use crate::{
     config::{self, CacheEntries},
-    udd_bugs::{load_bugs_from_reader, UDDBugs},
     BaseOptions, BinNMUsOptions,
 };
 
@@ -36,36 +36,40 @@ pub(crate) struct PrepareBinNMUs {
 impl PrepareBinNMUs {
     pub(crate) fn new(base_options: BaseOptions, options: PrepareBinNMUsOptions) -> Result<Self> {
         Ok(Self {
-            cache: config::Cache::new(base_options.force_download)?,
+            cache: config::Cache::new(&base_options)?,
             base_options,
             options,
         })
     }
 
     #[tokio::main]
-    async fn download_to_cache(&self, codename: &Codename) -> Result<()> {
+    async fn download_to_cache(&self, suite: &SuiteOrCodename) -> Result<()> {
         self.cache
-            .download(&[CacheEntries::FTBFSBugs(*codename)])
+            .download(&[
+                CacheEntries::Releases,
+                CacheEntries::FTBFSBugs(suite.clone()),
+            ])
             .await?;
         Ok(())
     }
 
-    fn load_bugs(&self, codename: &Codename) -> Result<UDDBugs> {
-        self.download_to_cache(codename)?;
-        load_bugs_from_reader(
-            self.cache
-                .get_cache_bufreader(format!("udd-ftbfs-bugs-{}.yaml", codename))?,
-        )
+    fn load_bugs(&self, suite: &SuiteOrCodename) -> Result<UDDBugs> {
+        self.download_to_cache(suite)?;
+        Ok(udd_bugs::from_reader(BufReader::new(File::open(
+            self.cache.get_ftbfs_bugs_path(suite)?,
+        )?))?)
     }
 
     pub(crate) fn run(self) -> Result<()> {
-        let codename: Codename = self.options.binnmu_options.suite.into();
         let ftbfs_bugs = if !self.base_options.force_processing {
-            self.load_bugs(&codename)?
+            self.load_bugs(&self.options.binnmu_options.suite)?
         } else {
             UDDBugs::new(vec![])
         };
 
+        let suite = self
+            .cache
+            .resolve_suite(&self.options.binnmu_options.suite)?;
         let matcher = regex::Regex::new("([a-z0-9+.-]+)[ \t].* \\(?([0-9][^() \t]*)\\)?")?;
 
         let reader: Box<dyn BufRead> = match &self.options.input {
@@ -89,7 +93,10 @@ impl PrepareBinNMUs {
                     _ => continue,
                 };
 
-                if let Some(bugs) = ftbfs_bugs.bugs_for_source(source) {
+                if let Some(bugs) = ftbfs_bugs
+                    .bugs_for_source(source, Some(suite))
+                    .filter(|bugs| bugs.iter().any(|bug| bug.is_rc()))
+                {
                     println!("# Skipping {} due to FTBFS bugs ...", source);
                     for bug in bugs {
                         println!("#   {} ({}): {}", bug.id, bug.severity, bug.title);
@@ -52,7 +52,7 @@ pub(crate) struct ProcessExcuses {
 impl ProcessExcuses {
     pub(crate) fn new(base_options: BaseOptions, options: ProcessExcusesOptions) -> Result<Self> {
         Ok(Self {
-            cache: config::Cache::new(base_options.force_download)?,
+            cache: config::Cache::new(&base_options)?,
             base_options,
             options,
         })
@@ -129,7 +129,7 @@ impl ProcessExcuses {
                             debug!("{}: cannot binNMU arch: all", item.source);
                             return None;
                         }
-                        archs.push(WBArchitecture::Architecture(*arch));
+                        archs.push(WBArchitecture::Architecture(arch.clone()));
                     }
                 }
             }
@@ -0,0 +1,589 @@
+// Copyright 2022 Sebastian Ramacher
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    fs::File,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context, Result};
+use clap::Parser;
+use log::{debug, warn};
+use pgp::{
+    composed::{CleartextSignedMessage, Deserializable, SignedPublicKey, SignedPublicSubKey},
+    packet::{self, SignatureType},
+    types::{KeyDetails, KeyId, Timestamp},
+};
+
+const BEGIN_SIGNED_MESSAGE: &str = "-----BEGIN PGP SIGNED MESSAGE-----";
+const END_SIGNATURE: &str = "-----END PGP SIGNATURE-----";
+
+const DEFAULT_KEYRINGS: [&str; 2] = [
+    "/usr/share/keyrings/debian-keyring.gpg",
+    "/usr/share/keyrings/debian-maintainers.gpg",
+];
+
+#[derive(Debug, Parser)]
+pub(crate) struct SignatureOptions {
+    /// Verify the OpenPGP signatures of the input files
+    #[clap(long)]
+    verify_signatures: bool,
+    /// Keyring with maintainer keys to verify signatures against. Can be specified multiple times. If not specified, the Debian keyrings are used.
+    #[clap(long = "keyring", parse(from_os_str))]
+    keyrings: Vec<PathBuf>,
+    /// Keyring with buildd keys to verify signatures against. Can be specified multiple times. Without buildd keys, builds signed by a buildd cannot be verified.
+    #[clap(long = "buildd-keyring", parse(from_os_str))]
+    buildd_keyrings: Vec<PathBuf>,
+    /// Skip input files without a valid signature instead of only warning about them
+    #[clap(long)]
+    require_signature: bool,
+    /// Skip input files not verifiably signed by a maintainer, i.e., buildd builds and input files without a valid signature
+    #[clap(long)]
+    maintainer_builds_only: bool,
+}
+
+/// Signer of a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Signer {
+    /// E-mail address of the primary user id of the key
+    pub(crate) address: String,
+    /// Whether the key is a buildd key
+    pub(crate) buildd: bool,
+}
+
+impl Display for Signer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.buildd {
+            write!(f, "{} (buildd)", self.address)
+        } else {
+            write!(f, "{} (maintainer)", self.address)
+        }
+    }
+}
+
+/// State of the signature of a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Signature {
+    /// The file is not signed
+    Unsigned,
+    /// The signature could not be verified
+    Invalid(String),
+    /// The signature is valid and was made by the given signer
+    Valid(Signer),
+}
+
+impl Display for Signature {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Signature::Unsigned => write!(f, "not signed"),
+            Signature::Invalid(reason) => write!(f, "invalid signature ({})", reason),
+            Signature::Valid(signer) => write!(f, "signed by {}", signer),
+        }
+    }
+}
+
+impl Signature {
+    /// Return whether the signature is valid and was made by a maintainer
+    pub(crate) fn is_maintainer(&self) -> bool {
+        match self {
+            Signature::Valid(signer) => !signer.buildd,
+            _ => false,
+        }
+    }
+}
+
+/// Text of a file together with the state of its signature
+///
+/// For inline-signed files, the text is the signed text. Anything before or after the signed
+/// message is not part of it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SignedText {
+    pub(crate) signature: Signature,
+    pub(crate) text: String,
+}
+
+// Parse an inline-signed message from the first line with the armor header to the end of the
+// signature
+fn parse_signed_message(data: &str) -> Option<pgp::errors::Result<CleartextSignedMessage>> {
+    let start = if data.starts_with(BEGIN_SIGNED_MESSAGE) {
+        0
+    } else {
+        data.find(&format!("\n{}", BEGIN_SIGNED_MESSAGE))? + 1
+    };
+    let data = &data[start..];
+    let end = data
+        .find(&format!("\n{}", END_SIGNATURE))
+        .map_or(data.len(), |end| end + 1 + END_SIGNATURE.len());
+    Some(CleartextSignedMessage::from_string(&data[..end]).map(|(message, _)| message))
+}
+
+// Return the signed text of a message with the line endings of the file
+fn cleartext(message: &CleartextSignedMessage) -> String {
+    // the signed text is normalized to CRLF line endings and has no final line break
+    let mut text = message.signed_text().replace("\r\n", "\n");
+    text.push('\n');
+    text
+}
+
+/// Return the signed text of an inline-signed file without verifying the signature
+///
+/// Unsigned data is returned as is.
+pub(crate) fn strip_signature(data: &str) -> Result<String> {
+    match parse_signed_message(data) {
+        Some(message) => Ok(cleartext(
+            &message.map_err(|err| anyhow!("malformed signature: {}", err))?,
+        )),
+        None => Ok(data.to_owned()),
+    }
+}
+
+// Extract the e-mail address from a user id
+fn signer_from_uid(uid: &str) -> String {
+    match uid.rsplit_once('<') {
+        Some((_, address)) => address.trim_end_matches('>').to_owned(),
+        None => uid.to_owned(),
+    }
+}
+
+// Compute the expiration time from the most recent signature with a key expiration time
+fn expiration<'a>(
+    created: Timestamp,
+    signatures: impl Iterator<Item = &'a packet::Signature>,
+) -> Option<u64> {
+    signatures
+        .filter(|signature| signature.key_expiration_time().is_some())
+        .max_by_key(|signature| signature.created().map(Timestamp::as_secs))
+        .and_then(|signature| signature.key_expiration_time())
+        .filter(|duration| duration.as_secs() != 0)
+        .map(|duration| u64::from(created.as_secs()) + u64::from(duration.as_secs()))
+}
+
+/// A key of a keyring
+struct Key {
+    key: SignedPublicKey,
+    buildd: bool,
+}
+
+impl Key {
+    /// Return the e-mail address of the primary user id
+    fn address(&self) -> String {
+        let users = &self.key.details.users;
+        users
+            .iter()
+            .find(|user| user.is_primary())
+            .or_else(|| users.first())
+            .map_or_else(
+                || self.key.fingerprint().to_string(),
+                |user| signer_from_uid(&String::from_utf8_lossy(user.id.id())),
+            )
+    }
+
+    // Signatures on the key made by the key itself
+    fn self_signatures(&self) -> impl Iterator<Item = &packet::Signature> {
+        let key_id = self.key.legacy_key_id();
+        let fingerprint = self.key.fingerprint();
+        self.key
+            .details
+            .users
+            .iter()
+            .flat_map(|user| user.signatures.iter())
+            .chain(self.key.details.direct_signatures.iter())
+            .filter(move |signature| {
+                signature.issuer_key_id().contains(&&key_id)
+                    || signature.issuer_fingerprint().contains(&&fingerprint)
+            })
+    }
+
+    /// Check if the key (or the given subkey) was valid when the signature was made
+    fn check_validity(
+        &self,
+        subkey: Option<&SignedPublicSubKey>,
+        signature: &packet::Signature,
+    ) -> Result<(), &'static str> {
+        if !self.key.details.revocation_signatures.is_empty() {
+            return Err("revoked key");
+        }
+        if let Some(subkey) = subkey {
+            if subkey
+                .signatures
+                .iter()
+                .any(|signature| signature.typ() == Some(SignatureType::SubkeyRevocation))
+            {
+                return Err("revoked key");
+            }
+        }
+
+        // keys are checked at the time the signature was made, as buildinfo files are usually
+        // verified long after the build
+        let signed = match signature.created() {
+            Some(created) => u64::from(created.as_secs()),
+            None => return Err("signature without creation time"),
+        };
+        let expirations = [
+            expiration(self.key.created_at(), self.self_signatures()),
+            subkey.and_then(|subkey| {
+                expiration(
+                    subkey.key.created_at(),
+                    subkey
+                        .signatures
+                        .iter()
+                        .filter(|signature| signature.typ() == Some(SignatureType::SubkeyBinding)),
+                )
+            }),
+        ];
+        if expirations
+            .iter()
+            .flatten()
+            .any(|expiration| signed > *expiration)
+        {
+            return Err("expired key");
+        }
+        Ok(())
+    }
+}
+
+/// Keys to verify signatures against
+#[derive(Default)]
+pub(crate) struct Keyring {
+    keys: Vec<Key>,
+    // maps key ids of primary keys and subkeys to the key and the index of the subkey
+    key_ids: HashMap<KeyId, Vec<(usize, Option<usize>)>>,
+}
+
+impl fmt::Debug for Keyring {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Keyring")
+            .field("keys", &self.keys.len())
+            .finish()
+    }
+}
+
+impl Keyring {
+    /// Add all keys of a binary or ASCII-armored keyring
+    pub(crate) fn add_keyring(&mut self, path: impl AsRef<Path>, buildd: bool) -> Result<()> {
+        let path = path.as_ref();
+        let (keys, _) = SignedPublicKey::from_reader_many(
+            File::open(path).with_context(|| format!("Failed to open {}", path.display()))?,
+        )
+        .with_context(|| format!("Failed to read keyring {}", path.display()))?;
+        for key in keys {
+            match key {
+                Ok(key) => self.add_key(key, buildd),
+                Err(err) => debug!("{}: skipping key: {}", path.display(), err),
+            }
+        }
+        Ok(())
+    }
+
+    fn add_key(&mut self, key: SignedPublicKey, buildd: bool) {
+        let index = self.keys.len();
+        self.key_ids
+            .entry(key.legacy_key_id())
+            .or_default()
+            .push((index, None));
+        for (subkey_index, subkey) in key.public_subkeys.iter().enumerate() {
+            self.key_ids
+                .entry(subkey.legacy_key_id())
+                .or_default()
+                .push((index, Some(subkey_index)));
+        }
+        self.keys.push(Key { key, buildd });
+    }
+
+    /// Return whether the keyring contains no keys
+    pub(crate) fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    // Find the keys and subkeys that could have issued the signature
+    fn candidates(
+        &self,
+        signature: &packet::Signature,
+    ) -> Vec<(&Key, Option<&SignedPublicSubKey>)> {
+        let mut key_ids: Vec<KeyId> = signature.issuer_key_id().into_iter().copied().collect();
+        // v4 key ids are the low 64 bits of the fingerprint
+        for fingerprint in signature.issuer_fingerprint() {
+            let bytes = fingerprint.as_bytes();
+            if bytes.len() == 20 {
+                let mut key_id = [0u8; 8];
+                key_id.copy_from_slice(&bytes[12..]);
+                key_ids.push(KeyId::from(key_id));
+            }
+        }
+        key_ids.sort_by_key(|key_id| key_id.to_string());
+        key_ids.dedup();
+
+        key_ids
+            .iter()
+            .filter_map(|key_id| self.key_ids.get(key_id))
+            .flatten()
+            .map(|(index, subkey_index)| {
+                let key = &self.keys[*index];
+                (
+                    key,
+                    subkey_index.map(|subkey_index| &key.key.public_subkeys[subkey_index]),
+                )
+            })
+            .collect()
+    }
+
+    /// Verify the inline signature of the given data
+    ///
+    /// Returns the signed text, which is only verified if the signature is valid.
+    pub(crate) fn verify(&self, data: &str) -> SignedText {
+        let message = match parse_signed_message(data) {
+            Some(Ok(message)) => message,
+            Some(Err(err)) => {
+                return SignedText {
+                    signature: Signature::Invalid(format!("malformed signature: {}", err)),
+                    text: data.to_owned(),
+                }
+            }
+            None => {
+                return SignedText {
+                    signature: Signature::Unsigned,
+                    text: data.to_owned(),
+                }
+            }
+        };
+
+        SignedText {
+            signature: self.verify_message(&message),
+            text: cleartext(&message),
+        }
+    }
+
+    fn verify_message(&self, message: &CleartextSignedMessage) -> Signature {
+        let text = message.signed_text();
+        let mut result = None;
+        for signature in message.signatures() {
+            for (key, subkey) in self.candidates(signature) {
+                let verified = match subkey {
+                    Some(subkey) => signature.verify(subkey, text.as_bytes()),
+                    None => signature.verify(&key.key, text.as_bytes()),
+                };
+                if verified.is_err() {
+                    result.get_or_insert("bad signature");
+                    continue;
+                }
+                if let Err(reason) = key.check_validity(subkey, signature) {
+                    result.get_or_insert(reason);
+                    continue;
+                }
+
+                let address = key.address();
+                return Signature::Valid(Signer {
+                    buildd: key.buildd || address.ends_with("@buildd.debian.org"),
+                    address,
+                });
+            }
+        }
+        Signature::Invalid(result.unwrap_or("unknown key").to_owned())
+    }
+}
+
+/// Verifier of signatures according to the options
+pub(crate) struct SignatureVerifier {
+    keyring: Keyring,
+    require_signature: bool,
+    maintainer_builds_only: bool,
+}
+
+impl SignatureVerifier {
+    /// Check the signature of the given data according to the options
+    ///
+    /// Returns the signed text and the verified signer if the signature is valid. Fails if the
+    /// file should be skipped.
+    pub(crate) fn check(
+        &self,
+        path: impl AsRef<Path>,
+        data: &str,
+    ) -> Result<(String, Option<Signer>)> {
+        let SignedText { signature, text } = self.keyring.verify(data);
+        debug!("{}: {}", path.as_ref().display(), signature);
+        if self.maintainer_builds_only && !signature.is_maintainer() {
+            return Err(anyhow!("not a verified maintainer build ({})", signature));
+        }
+        match signature {
+            Signature::Valid(signer) => Ok((text, Some(signer))),
+            _ if self.require_signature => Err(anyhow!("{}", signature)),
+            _ => {
+                warn!("{}: {}", path.as_ref().display(), signature);
+                Ok((text, None))
+            }
+        }
+    }
+}
+
+impl SignatureOptions {
+    fn is_enabled(&self) -> bool {
+        self.verify_signatures
+            || !self.keyrings.is_empty()
+            || !self.buildd_keyrings.is_empty()
+            || self.require_signature
+            || self.maintainer_builds_only
+    }
+
+    /// Load the keyrings if signature verification is enabled
+    pub(crate) fn verifier(&self) -> Result<Option<SignatureVerifier>> {
+        if !self.is_enabled() {
+            return Ok(None);
+        }
+
+        let mut keyring = Keyring::default();
+        if self.keyrings.is_empty() {
+            for path in DEFAULT_KEYRINGS {
+                // not every system has all Debian keyrings installed
+                if let Err(err) = keyring.add_keyring(path, false) {
+                    warn!("{:#}", err);
+                }
+            }
+        } else {
+            for path in &self.keyrings {
+                keyring.add_keyring(path, false)?;
+            }
+        }
+        for path in &self.buildd_keyrings {
+            keyring.add_keyring(path, true)?;
+        }
+        if keyring.is_empty() {
+            return Err(anyhow!("No keys available to verify signatures"));
+        }
+
+        Ok(Some(SignatureVerifier {
+            keyring,
+            require_signature: self.require_signature,
+            maintainer_builds_only: self.maintainer_builds_only,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::{strip_signature, Keyring, Signature, SignatureVerifier, Signer};
+
+    // buildinfo of picnic signed by the test maintainer key
+    const SIGNED_BUILDINFO: &str = include_str!("../testdata/picnic_3.0.11-1_i386.buildinfo");
+    const BUILDINFO: &str =
+        include_str!("../assorted-debian-utils/testdata/picnic_3.0.11-1_i386.buildinfo");
+
+    fn testdata(name: &str) -> PathBuf {
+        [env!("CARGO_MANIFEST_DIR"), "testdata", name]
+            .iter()
+            .collect()
+    }
+
+    fn keyring(maintainer: &[&str], buildd: &[&str]) -> Keyring {
+        let mut keyring = Keyring::default();
+        for name in maintainer {
+            keyring.add_keyring(testdata(name), false).unwrap();
+        }
+        for name in buildd {
+            keyring.add_keyring(testdata(name), true).unwrap();
+        }
+        keyring
+    }
+
+    fn verifier(keyring: Keyring, maintainer_builds_only: bool) -> SignatureVerifier {
+        SignatureVerifier {
+            keyring,
+            require_signature: true,
+            maintainer_builds_only,
+        }
+    }
+
+    #[test]
+    fn valid() {
+        let signed = keyring(&["test-maintainer.asc"], &[]).verify(SIGNED_BUILDINFO);
+        assert_eq!(
+            signed.signature,
+            Signature::Valid(Signer {
+                address: "maintainer@example.org".into(),
+                buildd: false,
+            })
+        );
+        assert!(signed.signature.is_maintainer());
+        assert_eq!(signed.text, BUILDINFO);
+        assert_eq!(strip_signature(SIGNED_BUILDINFO).unwrap(), BUILDINFO);
+
+        let (text, signer) = verifier(keyring(&["test-maintainer.asc"], &[]), true)
+            .check("buildinfo", SIGNED_BUILDINFO)
+            .unwrap();
+        assert_eq!(text, BUILDINFO);
+        assert_eq!(signer.unwrap().address, "maintainer@example.org");
+    }
+
+    #[test]
+    fn buildd() {
+        let buildd_keyring = || keyring(&["test-other.asc"], &["test-maintainer.asc"]);
+        let signature = buildd_keyring().verify(SIGNED_BUILDINFO).signature;
+        assert_eq!(
+            signature,
+            Signature::Valid(Signer {
+                address: "maintainer@example.org".into(),
+                buildd: true,
+            })
+        );
+        assert!(!signature.is_maintainer());
+
+        assert!(
+            verifier(buildd_keyring(), false)
+                .check("buildinfo", SIGNED_BUILDINFO)
+                .unwrap()
+                .1
+                .unwrap()
+                .buildd
+        );
+        assert!(verifier(buildd_keyring(), true)
+            .check("buildinfo", SIGNED_BUILDINFO)
+            .is_err());
+    }
+
+    #[test]
+    fn invalid() {
+        let keyring = keyring(&["test-other.asc"], &[]);
+        assert_eq!(
+            keyring.verify(SIGNED_BUILDINFO).signature,
+            Signature::Invalid("unknown key".into())
+        );
+        let verifier = verifier(keyring, true);
+        assert!(verifier.check("buildinfo", SIGNED_BUILDINFO).is_err());
+
+        let keyring = self::keyring(&["test-maintainer.asc"], &[]);
+        let tampered = SIGNED_BUILDINFO.replace("Version: 3.0.11-1", "Version: 3.0.11-2");
+        assert_eq!(
+            keyring.verify(&tampered).signature,
+            Signature::Invalid("bad signature".into())
+        );
+
+        assert_eq!(keyring.verify(BUILDINFO).signature, Signature::Unsigned);
+        assert_eq!(keyring.verify(BUILDINFO).text, BUILDINFO);
+        assert!(self::verifier(keyring, false)
+            .check("buildinfo", BUILDINFO)
+            .is_err());
+    }
+
+    #[test]
+    fn outside_signed_text() {
+        let keyring = keyring(&["test-maintainer.asc"], &[]);
+        // fields before and after the signed message are not covered by the signature
+        let tampered = format!(
+            "Source: evil\nVersion: 1.0\n\n{}\nSource: evil\nBinary: evil\n",
+            SIGNED_BUILDINFO
+        );
+        let signed = keyring.verify(&tampered);
+        assert_eq!(
+            signed.signature,
+            Signature::Valid(Signer {
+                address: "maintainer@example.org".into(),
+                buildd: false,
+            })
+        );
+        assert_eq!(signed.text, BUILDINFO);
+        assert_eq!(strip_signature(&tampered).unwrap(), BUILDINFO);
+    }
+}
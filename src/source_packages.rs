@@ -1,13 +1,13 @@
 // Copyright 2021-2022 Sebastian Ramacher
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::io::BufRead;
 use std::path::Path;
 
 use anyhow::Result;
-use assorted_debian_utils::archive::MultiArch;
-use indicatif::{ProgressBar, ProgressIterator};
-use serde::Deserialize;
+use assorted_debian_utils::{archive::MultiArch, stanzas, version::PackageVersion};
+use serde::{Deserialize, Serialize};
 
 use crate::config;
 
@@ -16,61 +16,97 @@ use crate::config;
 struct BinaryPackage {
     source: Option<String>,
     package: String,
+    version: PackageVersion,
     #[serde(rename = "Multi-Arch")]
     multi_arch: Option<MultiArch>,
 }
 
+impl BinaryPackage {
+    /// Return name and version of the source package
+    fn source_and_version(self) -> Result<(String, PackageVersion)> {
+        match &self.source {
+            Some(source) => {
+                let mut split = source.split_whitespace();
+                let source_package = split.next().unwrap().to_owned();
+                // Source: source (version) is only set if the source version differs from the
+                // binary version
+                match split.next() {
+                    Some(version) => Ok((
+                        source_package,
+                        version
+                            .trim_start_matches('(')
+                            .trim_end_matches(')')
+                            .try_into()?,
+                    )),
+                    None => Ok((source_package, self.version.without_binnmu_version())),
+                }
+            }
+            // no Source set, so Source == Package
+            None => Ok((self.package, self.version.without_binnmu_version())),
+        }
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct ParsedPackages {
+    ma_same_sources: HashSet<String>,
+    source_versions: HashMap<String, HashSet<PackageVersion>>,
+}
+
+#[derive(Default)]
 pub struct SourcePackages {
     ma_same_sources: HashSet<String>,
+    source_versions: HashMap<String, HashSet<PackageVersion>>,
 }
 
 impl SourcePackages {
     pub fn new<P>(paths: &[P]) -> Result<Self>
     where
-        P: AsRef<Path>,
+        P: AsRef<Path> + Sync,
     {
         let mut ma_same_sources = HashSet::<String>::new();
-        for path in paths {
-            let sources = Self::parse_packages(path);
-            ma_same_sources.extend(sources?);
+        let mut source_versions = HashMap::<String, HashSet<PackageVersion>>::new();
+        for parsed in config::process_in_parallel(paths, "source-packages", Self::parse_packages)? {
+            ma_same_sources.extend(parsed.ma_same_sources);
+            for (source, versions) in parsed.source_versions {
+                source_versions.entry(source).or_default().extend(versions);
+            }
         }
 
-        Ok(Self { ma_same_sources })
+        Ok(Self {
+            ma_same_sources,
+            source_versions,
+        })
     }
 
-    fn parse_packages<P>(path: P) -> Result<HashSet<String>>
-    where
-        P: AsRef<Path>,
-    {
-        // read Package file
-        let binary_packages: Vec<BinaryPackage> = rfc822_like::from_file(path.as_ref())?;
-        let pb = ProgressBar::new(binary_packages.len() as u64);
-        pb.set_style(config::default_progress_style().template(
-            "{msg}: {spinner:.green} [{wide_bar:.cyan/blue}] {pos}/{len} ({per_sec}, {eta})",
-        ));
-        pb.set_message(format!(
-            "Processing {}",
-            path.as_ref().file_name().unwrap().to_str().unwrap()
-        ));
-        // collect all sources with MA: same binaries
-        let ma_same_sources: HashSet<String> = binary_packages
-            .into_iter()
-            .progress_with(pb)
-            .filter(|binary_package| binary_package.multi_arch == Some(MultiArch::Same))
-            .map(|binary_package| {
-                if let Some(source_package) = &binary_package.source {
-                    source_package.split_whitespace().next().unwrap().into()
-                } else {
-                    // no Source set, so Source == Package
-                    binary_package.package
-                }
-            })
-            .collect();
+    fn parse_packages(reader: &mut dyn BufRead) -> Result<ParsedPackages> {
+        // read Package file one stanza at a time
+        // collect all sources with MA: same binaries and all source versions
+        let mut parsed = ParsedPackages::default();
+        for binary_package in stanzas::from_reader::<_, BinaryPackage>(reader) {
+            let binary_package = binary_package?;
+            let is_ma_same = binary_package.multi_arch == Some(MultiArch::Same);
+            let (source, version) = binary_package.source_and_version()?;
+            if is_ma_same {
+                parsed.ma_same_sources.insert(source.clone());
+            }
+            parsed
+                .source_versions
+                .entry(source)
+                .or_default()
+                .insert(version);
+        }
 
-        Ok(ma_same_sources)
+        Ok(parsed)
     }
 
     pub fn is_ma_same(&self, source: &str) -> bool {
         self.ma_same_sources.contains(source)
     }
+
+    pub fn has_version(&self, source: &str, version: &PackageVersion) -> bool {
+        self.source_versions
+            .get(source)
+            .is_some_and(|versions| versions.contains(version))
+    }
 }
@@ -1,31 +1,34 @@
 // Copyright 2022 Sebastian Ramacher
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{hash_map::Entry, BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt::{self, Display, Formatter};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
 
-use anyhow::Result;
-use assorted_debian_utils::architectures::{Architecture, RELEASE_ARCHITECTURES};
-use assorted_debian_utils::archive::Suite;
+use anyhow::{anyhow, Result};
+use assorted_debian_utils::architectures::Architecture;
+use assorted_debian_utils::archive::{Suite, SuiteOrCodename};
+use assorted_debian_utils::contents;
+use assorted_debian_utils::contents_diff::{
+    aliased_collisions, diff, diff_with, is_aliased, Change, ContentsMap, Partition,
+};
+use assorted_debian_utils::relations::Relation;
+use assorted_debian_utils::stanzas;
+use assorted_debian_utils::udd_bugs::{self, UDDBugs, UDDBugsQuery};
+use assorted_debian_utils::version::PackageVersion;
 use clap::Parser;
 use log::{debug, info, trace, warn};
-use smallvec::SmallVec;
-use smartstring::{LazyCompact, SmartString};
+use rayon::prelude::*;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     config::{self, CacheEntries},
     BaseOptions,
 };
 
-type SmallString = SmartString<LazyCompact>;
-// if there is a file in more than one package, the most common case are two packages
-type LoadIterator = dyn Iterator<Item = (SmallString, SmallVec<[SmallString; 2]>)>;
-
-fn strip_section(package: &str) -> SmallString {
-    package.split_once('/').map_or(package, |(_, p)| p).into()
-}
-
 fn compute_path_to_test(path: impl AsRef<str>) -> String {
     if let Some(stripped) = path.as_ref().strip_prefix("usr/") {
         stripped.into()
@@ -34,220 +37,814 @@ fn compute_path_to_test(path: impl AsRef<str>) -> String {
     }
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct BinaryPackage {
+    package: String,
+    version: PackageVersion,
+    source: Option<String>,
+    maintainer: String,
+    replaces: Option<String>,
+    breaks: Option<String>,
+}
+
+/// Version, source, maintainer and relations of a binary package
+#[derive(Debug, Serialize, Deserialize)]
+struct PackageInfo {
+    version: PackageVersion,
+    source: String,
+    maintainer: String,
+    replaces: Vec<Relation>,
+    breaks: Vec<Relation>,
+}
+
+fn parse_relations(package: &str, field: Option<&str>) -> Vec<Relation> {
+    field.map_or_else(Vec::new, |field| {
+        field
+            .split(',')
+            .filter_map(|relation| {
+                let relation = relation.trim();
+                match Relation::try_from(relation) {
+                    Ok(relation) => Some(relation),
+                    Err(_) => {
+                        warn!("{}: unable to parse relation '{}'", package, relation);
+                        None
+                    }
+                }
+            })
+            .collect()
+    })
+}
+
+fn parse_packages(reader: &mut dyn BufRead) -> Result<HashMap<String, PackageInfo>> {
+    let mut packages = HashMap::new();
+    for binary_package in stanzas::from_reader::<_, BinaryPackage>(reader) {
+        let binary_package = binary_package?;
+        let info = PackageInfo {
+            replaces: parse_relations(&binary_package.package, binary_package.replaces.as_deref()),
+            breaks: parse_relations(&binary_package.package, binary_package.breaks.as_deref()),
+            version: binary_package.version,
+            // Source: source (version) is only set if the source differs from the binary package
+            source: binary_package.source.map_or_else(
+                || binary_package.package.clone(),
+                |source| {
+                    source
+                        .split_whitespace()
+                        .next()
+                        .unwrap_or_default()
+                        .to_string()
+                },
+            ),
+            maintainer: binary_package.maintainer,
+        };
+        packages.insert(binary_package.package, info);
+    }
+    Ok(packages)
+}
+
+/// How relations cover a version of a package
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Coverage {
+    /// No relation applies
+    None,
+    /// Only relations without version constraint apply
+    Unversioned,
+    /// A relation with a version constraint satisfied by the version applies
+    Versioned,
+}
+
+/// Check how the relations cover the given version of a package
+fn relation_coverage(
+    relations: &[Relation],
+    package: &str,
+    version: Option<&PackageVersion>,
+) -> Coverage {
+    let mut coverage = Coverage::None;
+    for relation in relations
+        .iter()
+        .filter(|relation| relation.package == package)
+    {
+        match (&relation.version_constraint, version) {
+            (None, _) => coverage = Coverage::Unversioned,
+            (Some(constraint), Some(version)) if constraint.is_satisfied_by(version) => {
+                return Coverage::Versioned
+            }
+            _ => {}
+        }
+    }
+    coverage
+}
+
+/// Classification of a file moving between / and /usr and across packages
+///
+/// Following DEP 17, the new owners of a file need to declare versioned `Breaks` and `Replaces`
+/// against the old owners. Otherwise dpkg may delete the file when the old owner is upgraded or
+/// removed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Classification {
+    /// Versioned `Breaks` and `Replaces` are declared
+    Safe,
+    /// `Breaks` and `Replaces` are declared, but at least one of them is unversioned
+    Unversioned,
+    /// Only `Replaces` is declared
+    MissingBreaks,
+    /// Only `Breaks` is declared
+    MissingReplaces,
+    /// Neither `Breaks` nor `Replaces` is declared
+    AliasingLossRisk,
+}
+
+impl Display for Classification {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Classification::Safe => write!(f, "safe"),
+            Classification::Unversioned => write!(f, "unversioned Breaks/Replaces"),
+            Classification::MissingBreaks => write!(f, "missing-Breaks"),
+            Classification::MissingReplaces => write!(f, "missing-Replaces"),
+            Classification::AliasingLossRisk => write!(f, "aliasing-loss risk"),
+        }
+    }
+}
+
+/// Binary packages of the old and the new suite
+#[derive(Debug, Default)]
+struct PackageData {
+    old: HashMap<String, PackageInfo>,
+    new: HashMap<String, PackageInfo>,
+}
+
+impl PackageData {
+    // Information on a binary package, preferably from the new suite
+    fn info(&self, package: &str) -> Option<&PackageInfo> {
+        self.new.get(package).or_else(|| self.old.get(package))
+    }
+
+    /// Return the source package of a binary package
+    fn source(&self, package: &str) -> String {
+        self.info(package)
+            .map_or_else(|| package.to_string(), |info| info.source.clone())
+    }
+
+    /// Return the maintainer of a binary package
+    fn maintainer(&self, package: &str) -> String {
+        self.info(package).map_or_else(
+            || "Unknown maintainer".to_string(),
+            |info| info.maintainer.clone(),
+        )
+    }
+
+    /// Classify a file shipped by `old_packages` in the old suite and by `new_packages` in the
+    /// new suite
+    ///
+    /// Every package that no longer ships the file is checked against every package shipping
+    /// the file in the new suite, and the worst result is returned.
+    fn classify(
+        &self,
+        old_packages: &HashSet<&str>,
+        new_packages: &HashSet<&str>,
+    ) -> Classification {
+        let mut classification = Classification::Safe;
+        for old_owner in old_packages.difference(new_packages) {
+            let old_version = self.old.get(*old_owner).map(|info| &info.version);
+            for new_owner in new_packages {
+                let (breaks, replaces) = match self.new.get(*new_owner) {
+                    Some(info) => (
+                        relation_coverage(&info.breaks, old_owner, old_version),
+                        relation_coverage(&info.replaces, old_owner, old_version),
+                    ),
+                    None => (Coverage::None, Coverage::None),
+                };
+                classification = classification.max(match (breaks, replaces) {
+                    (Coverage::Versioned, Coverage::Versioned) => Classification::Safe,
+                    (Coverage::None, Coverage::None) => Classification::AliasingLossRisk,
+                    (Coverage::None, _) => Classification::MissingBreaks,
+                    (_, Coverage::None) => Classification::MissingReplaces,
+                    _ => Classification::Unversioned,
+                });
+            }
+        }
+        classification
+    }
+}
+
+/// A file that moved between / and /usr
+#[derive(Debug)]
+struct Finding {
+    /// Description of the move
+    description: String,
+    /// Packages shipping the file in the old or the new suite
+    packages: BTreeSet<String>,
+}
+
+impl Finding {
+    fn new(
+        description: String,
+        old_packages: &HashSet<&str>,
+        new_packages: &HashSet<&str>,
+    ) -> Self {
+        Self {
+            description,
+            packages: old_packages
+                .union(new_packages)
+                .map(|package| package.to_string())
+                .collect(),
+        }
+    }
+}
+
+/// Group findings by maintainer and source package
+///
+/// A finding involving packages built from multiple source packages is listed for each of them.
+fn group_findings<'a>(
+    findings: &'a [Finding],
+    packages: &PackageData,
+) -> BTreeMap<String, BTreeMap<String, Vec<&'a Finding>>> {
+    let mut grouped: BTreeMap<String, BTreeMap<String, Vec<&Finding>>> = BTreeMap::new();
+    for finding in findings {
+        let sources: BTreeMap<String, String> = finding
+            .packages
+            .iter()
+            .map(|package| (packages.source(package), packages.maintainer(package)))
+            .collect();
+        for (source, maintainer) in sources {
+            grouped
+                .entry(maintainer)
+                .or_default()
+                .entry(source)
+                .or_default()
+                .push(finding);
+        }
+    }
+    grouped
+}
+
+/// Add the findings of each check to the findings of the same check in other partitions
+///
+/// This keeps the findings in the order of the checks independent of the number of partitions.
+fn collect_findings(
+    findings: &mut Vec<Vec<Finding>>,
+    results: Vec<Result<Vec<Finding>>>,
+) -> Result<()> {
+    findings.resize_with(results.len(), Vec::new);
+    for (findings, result) in findings.iter_mut().zip(results) {
+        findings.extend(result?);
+    }
+    Ok(())
+}
+
+// paths known to not be affected: there are no packages with files in boot/, usr/etc/,
+// usr/lib/modules/, ...
+const DEFAULT_EXCLUDE: &str = "^(boot|etc|lib/modules|usr/src|var)/";
+
+/// Filter for the paths that are checked
+///
+/// A path is checked if it matches one of the include patterns (or if there are none) and none
+/// of the exclude patterns.
+#[derive(Debug, Default)]
+struct PathFilter {
+    include: Vec<Regex>,
+    exclude: Vec<Regex>,
+}
+
+impl PathFilter {
+    /// Parse patterns from a reader
+    ///
+    /// Each line is either `include <regex>` or `exclude <regex>`. Empty lines and lines starting
+    /// with `#` are ignored.
+    fn from_reader(reader: impl BufRead) -> Result<Self> {
+        let mut filter = Self::default();
+        for line in reader.lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (kind, pattern) = line
+                .split_once(char::is_whitespace)
+                .ok_or_else(|| anyhow!("invalid pattern line: {}", line))?;
+            let pattern = Regex::new(pattern.trim())?;
+            match kind {
+                "include" => filter.include.push(pattern),
+                "exclude" => filter.exclude.push(pattern),
+                _ => return Err(anyhow!("invalid pattern kind: {}", kind)),
+            }
+        }
+        Ok(filter)
+    }
+
+    fn matches(&self, path: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|pattern| pattern.is_match(path)))
+            && !self.exclude.iter().any(|pattern| pattern.is_match(path))
+    }
+}
+
 #[derive(Debug, Parser)]
 pub(crate) struct UsrMergedOptions {
     /// Also include files that only moved between / and /usr but stayed in the same package
     #[clap(long)]
     include_moved_in_package: bool,
     /// Do not skip some paths known to not be affected
+    ///
+    /// By default, paths in boot/, etc/, lib/modules/, usr/src/ and var/ are skipped, and only
+    /// paths in the directories aliased on merged-/usr systems (bin/, sbin/ and lib*/ and their
+    /// counterparts in usr/) are checked. With this option, files moving between any path and
+    /// the same path below usr/ are reported.
     #[clap(long)]
     no_skip: bool,
+    /// Suite or codename to compare from
+    #[clap(long, default_value = "stable")]
+    from: SuiteOrCodename,
+    /// Suite or codename to compare to
+    #[clap(long, default_value = "testing")]
+    to: SuiteOrCodename,
+    /// Only check these architectures (all is always checked)
+    #[clap(short, long)]
+    architecture: Option<Vec<Architecture>>,
+    /// File with include and exclude patterns for the checked paths
+    ///
+    /// Each line is either `include <regex>` or `exclude <regex>`. The patterns are matched
+    /// against paths without leading slash.
+    #[clap(long)]
+    patterns: Option<PathBuf>,
+    /// Instead of comparing two suites, check the suite to compare to for files shipped at both
+    /// of their aliased paths by different packages
+    ///
+    /// The suite to compare from is not used in this mode.
+    #[clap(long)]
+    collisions: bool,
+    /// Usertag of the bugs filed for the findings
+    ///
+    /// Can be given multiple times to look up bugs with any of the usertags, e.g., for the
+    /// different problems described in DEP-17.
+    #[clap(long = "usertag", default_value = "dep17p1")]
+    usertags: Vec<String>,
+    /// User of the usertags
+    #[clap(long, default_value = "helmutg@debian.org")]
+    usertag_user: String,
 }
 
 pub(crate) struct UsrMerged {
     cache: config::Cache,
     options: UsrMergedOptions,
+    old_suite: Suite,
+    new_suite: Suite,
+    filter: PathFilter,
 }
 
 impl UsrMerged {
     pub(crate) fn new(base_options: BaseOptions, options: UsrMergedOptions) -> Result<Self> {
+        let cache = config::Cache::new(&base_options)?;
+        Self::download_releases(&cache)?;
+        let old_suite = cache.resolve_suite(&options.from)?;
+        let new_suite = cache.resolve_suite(&options.to)?;
+
+        let mut filter = match &options.patterns {
+            Some(path) => PathFilter::from_reader(BufReader::new(File::open(path)?))?,
+            None => PathFilter::default(),
+        };
+        if !options.no_skip {
+            filter.exclude.push(Regex::new(DEFAULT_EXCLUDE)?);
+        }
+
         Ok(Self {
-            cache: config::Cache::new(base_options.force_download)?,
+            cache,
             options,
+            old_suite,
+            new_suite,
+            filter,
         })
     }
 
+    #[tokio::main]
+    async fn download_releases(cache: &config::Cache) -> Result<()> {
+        cache.download(&[CacheEntries::Releases]).await?;
+        Ok(())
+    }
+
+    /// Queries for the bugs with one of the usertags
+    fn bugs_queries(&self) -> Vec<UDDBugsQuery> {
+        self.options
+            .usertags
+            .iter()
+            .map(|tag| {
+                let mut query = UDDBugsQuery::new();
+                query.with_usertag(&self.options.usertag_user, tag);
+                query
+            })
+            .collect()
+    }
+
+    /// Contents files of the selected architectures and all of a suite
+    fn contents_entries(&self, suite: Suite) -> Result<Vec<CacheEntries>> {
+        Ok(self
+            .selected_architectures(self.cache.architectures(suite)?)
+            .into_iter()
+            .chain([Architecture::All])
+            .map(|architecture| CacheEntries::ArchitectureContents(suite, architecture))
+            .collect())
+    }
+
     #[tokio::main]
     async fn download_to_cache(&self) -> Result<()> {
-        self.cache
-            .download(&[
-                CacheEntries::Contents(Suite::Stable(None)),
-                CacheEntries::Contents(Suite::Testing(None)),
-            ])
-            .await?;
+        let mut entries = self.contents_entries(self.new_suite)?;
+        entries.push(CacheEntries::SuitePackages(self.new_suite));
+        // collisions are only checked in the new suite
+        if !self.options.collisions {
+            entries.extend(self.contents_entries(self.old_suite)?);
+            entries.push(CacheEntries::SuitePackages(self.old_suite));
+        }
+        entries.extend(self.bugs_queries().into_iter().map(CacheEntries::UDDBugs));
+        self.cache.download(&entries).await?;
         Ok(())
     }
 
-    fn load_contents_iter(&self, suite: Suite, arch: Architecture) -> Result<Box<LoadIterator>> {
-        for (architecture, path) in self.cache.get_content_paths(suite)? {
-            if arch != architecture {
-                continue;
-            }
+    /// Restrict architectures to the ones selected on the command line
+    fn selected_architectures(&self, architectures: Vec<Architecture>) -> Vec<Architecture> {
+        match &self.options.architecture {
+            Some(selected) => architectures
+                .into_iter()
+                .filter(|architecture| selected.contains(architecture))
+                .collect(),
+            None => architectures,
+        }
+    }
 
-            log::debug!(
-                "Processing contents for {} on {}: {:?}",
-                suite,
-                architecture,
-                path
-            );
-
-            let no_skip = self.options.no_skip;
-            let reader = BufReader::new(File::open(path)?);
-            return Ok(Box::new(reader.lines().filter_map(move |line| {
-                let line = match line {
-                    Ok(line) => line,
-                    _ => {
-                        return None;
+    /// Load the binary packages of a suite from all architectures
+    ///
+    /// If a package has different versions on different architectures, the highest version is
+    /// kept.
+    fn load_packages(&self, suite: Suite) -> Result<HashMap<String, PackageInfo>> {
+        let paths: Vec<PathBuf> = self
+            .cache
+            .get_suite_package_paths(suite)?
+            .into_iter()
+            .map(|(_, path)| path)
+            .collect();
+        let mut packages = HashMap::new();
+        for parsed in config::process_in_parallel(&paths, "usrmerged-packages", parse_packages)? {
+            for (package, info) in parsed {
+                match packages.entry(package) {
+                    Entry::Vacant(entry) => {
+                        entry.insert(info);
                     }
-                };
-                trace!("Processing: {}", line);
-
-                let mut split = line.split_whitespace();
-                let (path, packages) = match (split.next(), split.next()) {
-                    (Some(path), Some(packages)) => (path, packages),
-                    _ => {
-                        warn!("Unable to process line: {}", line);
-                        return None;
+                    Entry::Occupied(mut entry) => {
+                        if info.version > entry.get().version {
+                            entry.insert(info);
+                        }
                     }
-                };
+                }
+            }
+        }
+        Ok(packages)
+    }
 
-                // there are no packages with files in boot/, usr/etc/, usr/lib/modules/, ...
-                if !no_skip
-                    && ["boot/", "etc/", "lib/modules/", "usr/src/", "var/"]
-                        .into_iter()
-                        .any(|prefix| path.starts_with(prefix))
-                {
-                    debug!("Skipping {}", path);
-                    return None;
+    /// Load the files of a suite on `arch` in the partition
+    fn load_contents(
+        &self,
+        suite: Suite,
+        arch: &Architecture,
+        partition: Partition,
+    ) -> Result<ContentsMap> {
+        let path = self.cache.get_content_path(suite, arch)?;
+        debug!(
+            "Processing contents for {} on {} (partition {}): {:?}",
+            suite, arch, partition, path
+        );
+        let mut contents = ContentsMap::new();
+        for entry in contents::from_file(path)? {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(err) => {
+                    warn!("Unable to process entry: {}", err);
+                    continue;
                 }
+            };
+            if !partition.contains(&entry.path) {
+                continue;
+            }
+            trace!("Processing: {:?}", entry);
 
-                Some((
-                    path.into(),
-                    packages.split(',').map(strip_section).collect(),
-                ))
-            })));
-        }
+            // only files in the aliased directories are affected by the /usr-merge; this also
+            // keeps the maps of the Contents files small
+            if !self.options.no_skip && !is_aliased(&entry.path) {
+                trace!("Skipping {}: not in an aliased directory", entry.path);
+                continue;
+            }
 
-        unreachable!("This will never be reached.");
+            contents
+                .entry(entry.path)
+                .or_default()
+                .extend(entry.packages.into_iter().map(|package| package.package));
+        }
+        Ok(contents)
     }
 
-    fn load_contents(
+    /// Load the files of a suite on `architecture` with the files of all merged in
+    ///
+    /// If no architecture is given, e.g., because the suite does not contain it, only the files
+    /// of all are returned.
+    fn load_merged_contents(
         &self,
         suite: Suite,
-        arch: Architecture,
-    ) -> Result<HashMap<SmallString, SmallVec<[SmallString; 2]>>> {
-        Ok(HashMap::from_iter(self.load_contents_iter(suite, arch)?))
+        architecture: Option<&Architecture>,
+        all_contents: &ContentsMap,
+        partition: Partition,
+    ) -> Result<ContentsMap> {
+        let mut contents = match architecture {
+            Some(architecture) => self.load_contents(suite, architecture, partition)?,
+            None => ContentsMap::new(),
+        };
+        for (path, packages) in all_contents {
+            contents
+                .entry(path.clone())
+                .or_default()
+                .extend(packages.iter().cloned());
+        }
+        Ok(contents)
     }
 
-    pub(crate) fn run(self) -> Result<()> {
-        self.download_to_cache()?;
+    /// Split the paths into partitions such that the files of the given architectures of each
+    /// suite, each merged with the files of all, fit into memory
+    fn partitions(&self, suites: &[(Suite, &[Architecture])]) -> Result<Vec<Partition>> {
+        let mut paths = vec![];
+        for (suite, architectures) in suites {
+            paths.push(self.cache.get_content_path(*suite, &Architecture::All)?);
+            for architecture in architectures.iter() {
+                paths.push(self.cache.get_content_path(*suite, architecture)?);
+                paths.push(self.cache.get_content_path(*suite, &Architecture::All)?);
+            }
+        }
+        config::contents_partitions(&paths)
+    }
 
-        // Check if file from stable on $architecture moved to other
-        // packages on testing on $architecture | all. If architecture ==
-        // all, this will check all -> all.
-        let mut testing_all_file_map =
-            self.load_contents(Suite::Testing(None), Architecture::All)?;
-        for architecture in RELEASE_ARCHITECTURES
-            .into_iter()
-            .chain([Architecture::All].into_iter())
-        {
-            let testing_file_map = if architecture != Architecture::All {
-                self.load_contents(Suite::Testing(None), architecture)?
-            } else {
-                HashMap::new()
+    /// Compare the files of the old and the new suite
+    ///
+    /// With `--no-skip`, files moving between any path and the same path below usr/ are
+    /// considered to be moved between directories.
+    fn diff(&self, old: &ContentsMap, new: &ContentsMap) -> Vec<Change> {
+        if self.options.no_skip {
+            diff_with(old, new, |path| Some(compute_path_to_test(path)))
+        } else {
+            diff(old, new)
+        }
+    }
+
+    /// Turn the files that moved between / and /usr into findings
+    ///
+    /// Files that moved to other packages are classified by the `Breaks` and `Replaces` of the
+    /// new packages. Files that stayed in the same packages are only reported with
+    /// `--include-moved-in-package`.
+    fn findings<'a>(
+        &self,
+        architecture: &Architecture,
+        changes: impl IntoIterator<Item = &'a Change>,
+        packages: &PackageData,
+    ) -> Vec<Finding> {
+        let mut output = Vec::new();
+        for change in changes {
+            let (old_path, new_path, old_packages, new_packages) = match change {
+                Change::MovedBetweenDirectories {
+                    old_path,
+                    new_path,
+                    old_packages,
+                    new_packages,
+                } => (old_path, new_path, old_packages, new_packages),
+                _ => continue,
             };
+            if !self.filter.matches(old_path) {
+                debug!("Skipping {}", old_path);
+                continue;
+            }
 
-            for (path, stable_packages) in
-                self.load_contents_iter(Suite::Stable(None), architecture)?
-            {
-                let path_to_test = compute_path_to_test(&path);
-                debug!(
-                    "{}: processing {} - checking for {}",
-                    architecture, path, path_to_test
+            let old_packages_set: HashSet<&str> = old_packages.iter().map(|v| v.as_str()).collect();
+            let new_packages_set: HashSet<&str> = new_packages.iter().map(|v| v.as_str()).collect();
+            if old_packages != new_packages {
+                output.push(Finding::new(
+                    format!(
+                        "{}: {} => {}: {:?} vs {:?} ({})",
+                        architecture,
+                        old_path,
+                        new_path,
+                        old_packages,
+                        new_packages,
+                        packages.classify(&old_packages_set, &new_packages_set),
+                    ),
+                    &old_packages_set,
+                    &new_packages_set,
+                ));
+            } else if self.options.include_moved_in_package {
+                output.push(Finding::new(
+                    format!(
+                        "{}: {} => {}: {:?}",
+                        architecture, old_path, new_path, old_packages,
+                    ),
+                    &old_packages_set,
+                    &new_packages_set,
+                ));
+            } else {
+                info!(
+                    "Renamed {} to {} (packages {:?})",
+                    old_path, new_path, new_packages,
                 );
+            }
+        }
+        output
+    }
 
-                let testing_packages_set = match (
-                    testing_file_map.get(path_to_test.as_str()),
-                    testing_all_file_map.get(path_to_test.as_str()),
-                ) {
-                    (None, None) => {
-                        debug!("{}: {} not found", architecture, path_to_test);
-                        continue;
-                    }
-                    (None, Some(packages)) | (Some(packages), None) => {
-                        HashSet::from_iter(packages.iter().map(|v| v.as_str()))
-                    }
-                    (Some(arch_packages), Some(all_packages)) => HashSet::from_iter(
-                        arch_packages
-                            .iter()
-                            .chain(all_packages.iter())
-                            .map(|v| v.as_str()),
-                    ),
+    /// Check if different packages in the new suite on `architecture` or all ship a file at both
+    /// of its aliased paths
+    ///
+    /// Collisions between files of all are only reported when `architecture` is all.
+    fn check_collisions(
+        &self,
+        architecture: &Architecture,
+        new_all_contents: &ContentsMap,
+        partition: Partition,
+    ) -> Result<Vec<Finding>> {
+        let contents = self.load_merged_contents(
+            self.new_suite,
+            (*architecture != Architecture::All).then_some(architecture),
+            new_all_contents,
+            partition,
+        )?;
+
+        let mut output = Vec::new();
+        for collision in aliased_collisions(&contents) {
+            if !self.filter.matches(&collision.path)
+                && !self.filter.matches(&collision.aliased_path)
+            {
+                debug!("Skipping {}", collision.path);
+                continue;
+            }
+            if *architecture != Architecture::All {
+                let from_all = |path: &str, packages: &BTreeSet<String>| {
+                    new_all_contents
+                        .get(path)
+                        .is_some_and(|all_packages| packages.is_subset(all_packages))
                 };
+                // already reported for all
+                if from_all(&collision.path, &collision.packages)
+                    && from_all(&collision.aliased_path, &collision.aliased_packages)
+                {
+                    continue;
+                }
+            }
 
-                let stable_packages_set: HashSet<&str> =
-                    HashSet::from_iter(stable_packages.iter().map(|v| v.as_str()));
-                if stable_packages_set != testing_packages_set {
-                    println!(
-                        "{}: {} => {}: {:?} vs {:?}",
-                        architecture, path, path_to_test, stable_packages_set, testing_packages_set,
-                    );
-                } else if self.options.include_moved_in_package {
-                    println!(
-                        "{}: {} => {}: {:?}",
-                        architecture, path, path_to_test, stable_packages_set,
-                    );
-                } else {
-                    info!(
-                        "Renamed {} to {} (packages {:?})",
-                        path, path_to_test, testing_packages_set,
-                    );
+            let packages_set: HashSet<&str> =
+                collision.packages.iter().map(|v| v.as_str()).collect();
+            let aliased_packages_set: HashSet<&str> = collision
+                .aliased_packages
+                .iter()
+                .map(|v| v.as_str())
+                .collect();
+            output.push(Finding::new(
+                format!("{}: {}", architecture, collision),
+                &packages_set,
+                &aliased_packages_set,
+            ));
+        }
+        Ok(output)
+    }
+
+    /// Check all architectures of the new suite for aliased collisions
+    fn run_collisions(&self) -> Result<Vec<Finding>> {
+        let new_architectures =
+            self.selected_architectures(self.cache.architectures(self.new_suite)?);
+        let mut findings = Vec::new();
+        for partition in self.partitions(&[(self.new_suite, &new_architectures)])? {
+            let new_all_contents =
+                self.load_contents(self.new_suite, &Architecture::All, partition)?;
+            // architectures are processed in parallel, but the findings are kept in order
+            let results: Vec<Result<Vec<Finding>>> = new_architectures
+                .par_iter()
+                .chain([Architecture::All].par_iter())
+                .map(|architecture| {
+                    self.check_collisions(architecture, &new_all_contents, partition)
+                })
+                .collect();
+            collect_findings(&mut findings, results)?;
+        }
+        Ok(findings.into_iter().flatten().collect())
+    }
+
+    /// Check if files moved between / and /usr and to other packages
+    ///
+    /// Each architecture is compared with the files of all merged in on both sides, so files
+    /// moving between architecture-specific and architecture-independent packages are found as
+    /// well. Moves that are already found by comparing all with all are only reported for all.
+    fn run_compare(&self, packages: &PackageData) -> Result<Vec<Finding>> {
+        let old_architectures =
+            self.selected_architectures(self.cache.architectures(self.old_suite)?);
+        let new_architectures =
+            self.selected_architectures(self.cache.architectures(self.new_suite)?);
+        // architectures removed in the new suite are only compared with all and vice versa
+        let mut architectures = old_architectures.clone();
+        for architecture in &new_architectures {
+            if !architectures.contains(architecture) {
+                architectures.push(architecture.clone());
+            }
+        }
+
+        let mut findings = Vec::new();
+        for partition in self.partitions(&[
+            (self.old_suite, &old_architectures),
+            (self.new_suite, &new_architectures),
+        ])? {
+            let old_all_contents =
+                self.load_contents(self.old_suite, &Architecture::All, partition)?;
+            let new_all_contents =
+                self.load_contents(self.new_suite, &Architecture::All, partition)?;
+            let all_changes = self.diff(&old_all_contents, &new_all_contents);
+            let all_changes_set: HashSet<&Change> = all_changes.iter().collect();
+
+            // architectures are processed in parallel, but the findings are kept in order
+            let mut results: Vec<Result<Vec<Finding>>> = architectures
+                .par_iter()
+                .map(|architecture| {
+                    let old = self.load_merged_contents(
+                        self.old_suite,
+                        old_architectures
+                            .contains(architecture)
+                            .then_some(architecture),
+                        &old_all_contents,
+                        partition,
+                    )?;
+                    let new = self.load_merged_contents(
+                        self.new_suite,
+                        new_architectures
+                            .contains(architecture)
+                            .then_some(architecture),
+                        &new_all_contents,
+                        partition,
+                    )?;
+                    let changes = self.diff(&old, &new);
+                    Ok(self.findings(
+                        architecture,
+                        changes
+                            .iter()
+                            .filter(|change| !all_changes_set.contains(change)),
+                        packages,
+                    ))
+                })
+                .collect();
+            results.push(Ok(self.findings(
+                &Architecture::All,
+                &all_changes,
+                packages,
+            )));
+            collect_findings(&mut findings, results)?;
+        }
+        Ok(findings.into_iter().flatten().collect())
+    }
+
+    fn load_bugs(&self) -> Result<UDDBugs> {
+        // bugs with more than one of the usertags are only reported once
+        let mut ids = HashSet::new();
+        let mut bugs = vec![];
+        for query in self.bugs_queries() {
+            let path = self.cache.get_udd_bugs_path(&query)?;
+            for bug in udd_bugs::from_reader(File::open(path)?)?.iter() {
+                if ids.insert(bug.id) {
+                    bugs.push(bug.clone());
                 }
             }
         }
-        testing_all_file_map.clear();
-
-        // Check if file from stable on all moved to other
-        // packages on testing on $architecture for all architectures except all.
-        for testing_architecture in RELEASE_ARCHITECTURES.into_iter() {
-            let testing_file_map =
-                self.load_contents(Suite::Testing(None), testing_architecture)?;
-            for (path, stable_packages) in
-                self.load_contents_iter(Suite::Stable(None), Architecture::All)?
-            {
-                let path_to_test = compute_path_to_test(&path);
-                debug!(
-                    "{} -> {}: processing {} - checking for {}",
-                    Architecture::All,
-                    testing_architecture,
-                    path,
-                    path_to_test
-                );
+        Ok(UDDBugs::new(bugs))
+    }
 
-                let testing_packages_set = match testing_file_map.get(path_to_test.as_str()) {
-                    None => {
-                        debug!("{}: {} not found", Architecture::All, path_to_test);
-                        continue;
-                    }
-                    Some(packages) => HashSet::from_iter(packages.iter().map(|v| v.as_str())),
-                };
+    pub(crate) fn run(self) -> Result<()> {
+        self.download_to_cache()?;
+
+        let (packages, findings) = if self.options.collisions {
+            let packages = PackageData {
+                new: self.load_packages(self.new_suite)?,
+                ..Default::default()
+            };
+            (packages, self.run_collisions()?)
+        } else {
+            let packages = PackageData {
+                old: self.load_packages(self.old_suite)?,
+                new: self.load_packages(self.new_suite)?,
+            };
+            let findings = self.run_compare(&packages)?;
+            (packages, findings)
+        };
 
-                let stable_packages_set: HashSet<&str> =
-                    HashSet::from_iter(stable_packages.iter().map(|v| v.as_str()));
-                if stable_packages_set != testing_packages_set {
-                    println!(
-                        "{} -> {}: {} => {}: {:?} vs {:?}",
-                        Architecture::All,
-                        testing_architecture,
-                        path,
-                        path_to_test,
-                        stable_packages_set,
-                        testing_packages_set,
-                    );
-                } else if self.options.include_moved_in_package {
-                    println!(
-                        "{} -> {}: {} => {}: {:?}",
-                        Architecture::All,
-                        testing_architecture,
-                        path,
-                        path_to_test,
-                        stable_packages_set,
-                    );
-                } else {
-                    info!(
-                        "Renamed {} to {} (packages {:?})",
-                        path, path_to_test, testing_packages_set,
-                    );
+        let bugs = self.load_bugs()?;
+        for (maintainer, sources) in group_findings(&findings, &packages) {
+            println!("{}", maintainer);
+            for (source, findings) in sources {
+                match bugs.bugs_for_source(&source, None) {
+                    Some(bugs) => {
+                        println!("  {}: filed", source);
+                        for bug in bugs {
+                            println!("    #{}: {}", bug.id, bug.title);
+                        }
+                    }
+                    None => println!("  {}: new", source),
+                }
+                for finding in findings {
+                    println!("    {}", finding.description);
                 }
             }
         }
@@ -255,3 +852,165 @@ impl UsrMerged {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use regex::Regex;
+
+    use super::{
+        group_findings, parse_packages, Classification, Finding, PackageData, PathFilter,
+        DEFAULT_EXCLUDE,
+    };
+
+    const OLD: &str = r#"Package: old
+Version: 1.0-1
+Maintainer: Jane Doe <jane@example.org>
+
+Package: other
+Version: 2.0-1
+Source: other-source (2.0-1)
+Maintainer: John Doe <john@example.org>
+"#;
+
+    const NEW: &str = r#"Package: safe
+Version: 1.0-1
+Maintainer: Jane Doe <jane@example.org>
+Breaks: old (<< 1.1)
+Replaces: old (<< 1.1)
+
+Package: replaces
+Version: 1.0-1
+Maintainer: Jane Doe <jane@example.org>
+Replaces: old (<< 1.1)
+
+Package: breaks
+Version: 1.0-1
+Maintainer: Jane Doe <jane@example.org>
+Breaks: old (<< 1.0)
+Replaces: old (<< 1.0)
+
+Package: breaks-only
+Version: 1.0-1
+Maintainer: Jane Doe <jane@example.org>
+Breaks: old (<< 1.1)
+
+Package: unversioned
+Version: 1.0-1
+Maintainer: Jane Doe <jane@example.org>
+Breaks: old
+Replaces: old (<< 1.1)
+
+Package: unrelated
+Version: 1.0-1
+Maintainer: Jane Doe <jane@example.org>
+Breaks: other
+Replaces: other
+"#;
+
+    #[test]
+    fn classify() {
+        let packages = PackageData {
+            old: parse_packages(&mut OLD.as_bytes()).unwrap(),
+            new: parse_packages(&mut NEW.as_bytes()).unwrap(),
+        };
+        let old = HashSet::from(["old"]);
+        assert_eq!(
+            packages.classify(&old, &HashSet::from(["safe"])),
+            Classification::Safe
+        );
+        assert_eq!(
+            packages.classify(&old, &HashSet::from(["replaces"])),
+            Classification::MissingBreaks
+        );
+        assert_eq!(
+            packages.classify(&old, &HashSet::from(["breaks-only"])),
+            Classification::MissingReplaces
+        );
+        assert_eq!(
+            packages.classify(&old, &HashSet::from(["unversioned"])),
+            Classification::Unversioned
+        );
+        assert_eq!(
+            packages.classify(&old, &HashSet::from(["safe", "unversioned"])),
+            Classification::Unversioned
+        );
+        // the version constraint does not cover the version in the old suite
+        assert_eq!(
+            packages.classify(&old, &HashSet::from(["breaks"])),
+            Classification::AliasingLossRisk
+        );
+        assert_eq!(
+            packages.classify(&old, &HashSet::from(["unrelated"])),
+            Classification::AliasingLossRisk
+        );
+        assert_eq!(
+            packages.classify(&old, &HashSet::from(["safe", "replaces"])),
+            Classification::MissingBreaks
+        );
+        // the file stays in the same package
+        assert_eq!(
+            packages.classify(&old, &HashSet::from(["old", "safe"])),
+            Classification::Safe
+        );
+    }
+
+    #[test]
+    fn group() {
+        let packages = PackageData {
+            old: parse_packages(&mut OLD.as_bytes()).unwrap(),
+            new: parse_packages(&mut NEW.as_bytes()).unwrap(),
+        };
+        let findings = [
+            Finding::new(
+                "first".into(),
+                &HashSet::from(["old"]),
+                &HashSet::from(["safe"]),
+            ),
+            Finding::new(
+                "second".into(),
+                &HashSet::from(["other"]),
+                &HashSet::from(["unknown"]),
+            ),
+        ];
+        let grouped = group_findings(&findings, &packages);
+        assert_eq!(grouped.len(), 3);
+
+        let jane = &grouped["Jane Doe <jane@example.org>"];
+        assert_eq!(jane.len(), 2);
+        assert_eq!(jane["old"].len(), 1);
+        assert_eq!(jane["safe"][0].description, "first");
+
+        let john = &grouped["John Doe <john@example.org>"];
+        assert_eq!(john["other-source"][0].description, "second");
+        assert!(grouped["Unknown maintainer"].contains_key("unknown"));
+    }
+
+    #[test]
+    fn path_filter() {
+        let filter = PathFilter::default();
+        assert!(filter.matches("bin/sh"));
+
+        let patterns = r#"
+# only check libraries
+include ^(usr/)?lib/
+exclude ^lib/modules/
+"#;
+        let filter = PathFilter::from_reader(patterns.as_bytes()).unwrap();
+        assert!(filter.matches("lib/libfoo.so"));
+        assert!(filter.matches("usr/lib/libfoo.so"));
+        assert!(!filter.matches("bin/sh"));
+        assert!(!filter.matches("lib/modules/foo.ko"));
+
+        let mut filter = PathFilter::default();
+        filter.exclude.push(Regex::new(DEFAULT_EXCLUDE).unwrap());
+        assert!(!filter.matches("etc/foo"));
+        assert!(!filter.matches("usr/src/foo"));
+        assert!(filter.matches("usr/etc/foo"));
+
+        assert!(PathFilter::from_reader(&b"include"[..]).is_err());
+        assert!(PathFilter::from_reader(&b"foo bar"[..]).is_err());
+        assert!(PathFilter::from_reader(&b"include ("[..]).is_err());
+    }
+}